@@ -0,0 +1,205 @@
+use std::{collections::HashMap, ops::Range};
+
+use thulac_rs::{LatinTokenizer, Pinyin};
+
+use crate::FIELD_GAP;
+
+/// Turns the words of posts and queries into terms, the same way for both,
+/// so an index must be searched with the analyzer it was built with.
+///
+/// Each word is indexed lowercased, along with the sub-tokens of Latin words
+/// and, if a [`Pinyin`] dictionary is set, its pinyin and initials, all at
+/// the position of the word. Post references like `#123456`, tagged `pid` by
+/// [`thulac_rs::PatternRecognizer::hole`], are indexed whole, so they do not
+/// match the bare number.
+#[derive(Default)]
+pub struct Analyzer {
+    latin: LatinTokenizer,
+    pinyin: Option<Pinyin>,
+}
+
+/// A word of a query. It matches a position of a document holding all the
+/// terms of one of its alternatives.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryWord {
+    pub alternatives: Vec<Vec<String>>,
+}
+
+impl QueryWord {
+    /// A word matching a single term.
+    pub fn term(term: &str) -> Self {
+        Self {
+            alternatives: vec![vec![term.to_owned()]],
+        }
+    }
+}
+
+/// Whether a word of [`thulac_rs::Thulac::cut`] is indexed, not being
+/// whitespace or punctuation.
+fn indexed(word: &str, tag: &str) -> bool {
+    !word.is_empty() && tag != "w"
+}
+
+/// The tag of post references.
+const POST_ID: &str = "pid";
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_latin(mut self, latin: LatinTokenizer) -> Self {
+        self.latin = latin;
+        self
+    }
+    pub fn with_pinyin(mut self, pinyin: Pinyin) -> Self {
+        self.pinyin = Some(pinyin);
+        self
+    }
+    /// The word lowercased, then its Latin sub-tokens.
+    fn latin_terms(&self, range: Range<usize>, word: &str, tag: &str) -> Vec<String> {
+        if tag == POST_ID {
+            return vec![word.to_owned()];
+        }
+        self.latin
+            .terms(range, word)
+            .into_iter()
+            .map(|x| x.text)
+            .collect()
+    }
+    /// The terms of the fields of a document, the text of a post then its
+    /// replies, with their positions. Fields are a few positions apart, so
+    /// phrases do not span them.
+    pub fn document<S: AsRef<str>>(
+        &self,
+        fields: &[Vec<(Range<usize>, S, &str)>],
+    ) -> HashMap<String, Vec<u32>> {
+        let mut terms = HashMap::<String, Vec<u32>>::new();
+        let mut position = 0;
+        for (i, words) in fields.iter().enumerate() {
+            if i > 0 {
+                position += FIELD_GAP;
+            }
+            for (range, word, tag) in words.iter() {
+                let word = word.as_ref();
+                if !indexed(word, tag) {
+                    continue;
+                }
+                let pinyin = self
+                    .pinyin
+                    .iter()
+                    .flat_map(|x| x.terms(range.clone(), word));
+                for term in self
+                    .latin_terms(range.clone(), word, tag)
+                    .into_iter()
+                    .chain(pinyin.map(|x| x.text))
+                {
+                    let positions = terms.entry(term).or_default();
+                    if positions.last() != Some(&position) {
+                        positions.push(position);
+                    }
+                }
+                position += 1;
+            }
+        }
+        terms
+    }
+    /// The words of a query, each matching the lowercased word, all its
+    /// sub-tokens at one position, or its pinyin. Chinese words thus also
+    /// match posts written in pinyin, and homophones.
+    pub fn query<S: AsRef<str>>(&self, words: &[(Range<usize>, S, &str)]) -> Vec<QueryWord> {
+        words
+            .iter()
+            .filter(|(_, word, tag)| indexed(word.as_ref(), tag))
+            .map(|(range, word, tag)| {
+                let word = word.as_ref();
+                let mut terms = self.latin_terms(range.clone(), word, tag).into_iter();
+                let mut alternatives = vec![terms.next().into_iter().collect::<Vec<_>>()];
+                let parts = terms.collect::<Vec<_>>();
+                if !parts.is_empty() {
+                    alternatives.push(parts);
+                }
+                if let Some(annotation) = self.pinyin.as_ref().and_then(|x| x.annotate(word)) {
+                    if !alternatives.iter().any(|x| x[..] == [&annotation.full[..]]) {
+                        alternatives.push(vec![annotation.full]);
+                    }
+                }
+                QueryWord { alternatives }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<(Range<usize>, &str, &str)> {
+        let mut start = 0;
+        text.split(' ')
+            .map(|x| {
+                let (word, tag) = x.rsplit_once('_').unwrap();
+                let range = start..start + word.len();
+                start = range.end;
+                (range, word, tag)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_document() {
+        let pinyin = Pinyin::load_txt(&mut "树\tshù\n洞\tdòng\n".as_bytes()).unwrap();
+        let analyzer = Analyzer::new()
+            .with_latin(LatinTokenizer::with_stemming())
+            .with_pinyin(pinyin);
+        let terms =
+            analyzer.document(&[words("树洞_n ，_w RunningMan_x Run-run_x"), words("树洞_n")]);
+        let positions = |term: &str| terms.get(term).map(Vec::as_slice);
+        assert_eq!(positions("树洞"), Some(&[0, 19][..]));
+        assert_eq!(positions("shudong"), Some(&[0, 19][..]));
+        assert_eq!(positions("sd"), Some(&[0, 19][..]));
+        assert_eq!(positions("，"), None);
+        assert_eq!(positions("runningman"), Some(&[1][..]));
+        assert_eq!(positions("man"), Some(&[1][..]));
+        // both sub-tokens of `Run-run` give the same term at position 2
+        assert_eq!(positions("run"), Some(&[1, 2][..]));
+        assert_eq!(positions("run-run"), Some(&[2][..]));
+    }
+
+    #[test]
+    fn test_post_id() {
+        let analyzer = Analyzer::new();
+        let terms = analyzer.document(&[words("回复_v #123456_pid")]);
+        let mut terms = terms.into_iter().collect::<Vec<_>>();
+        terms.sort_unstable();
+        assert_eq!(
+            terms,
+            [("#123456".into(), vec![1]), ("回复".into(), vec![0])]
+        );
+        assert_eq!(
+            analyzer.query(&words("#123456_pid")),
+            [QueryWord::term("#123456")]
+        );
+    }
+
+    #[test]
+    fn test_query() {
+        let pinyin = Pinyin::load_txt(&mut "树\tshù\n洞\tdòng\n".as_bytes()).unwrap();
+        let analyzer = Analyzer::new()
+            .with_latin(LatinTokenizer::with_stemming())
+            .with_pinyin(pinyin);
+        let alternatives = |x: &[&[&str]]| QueryWord {
+            alternatives: x
+                .iter()
+                .map(|x| x.iter().map(|x| x.to_string()).collect())
+                .collect(),
+        };
+        assert_eq!(
+            analyzer.query(&words("树洞_n 。_w Runs_x shudong_x")),
+            [
+                alternatives(&[&["树洞"], &["shudong"]]),
+                alternatives(&[&["runs"], &["run"]]),
+                alternatives(&[&["shudong"]]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    iter,
+    path::Path,
+};
+
+use thulac_rs::{Dat, Result, Thulac};
+
+use crate::{varint, Analyzer, Post, OFFSETS, POSTINGS, POSTS, TERMS};
+
+/// Builds an index in memory from posts, then writes it with
+/// [`IndexBuilder::write`]. Posts are segmented by a [`Thulac`] and turned
+/// into terms by an [`Analyzer`].
+pub struct IndexBuilder<'t> {
+    thulac: &'t Thulac,
+    analyzer: Analyzer,
+    postings: Postings,
+}
+
+/// The postings of an index being built, independent of tokenization.
+#[derive(Default)]
+struct Postings {
+    /// The documents of each term, in order, with the positions of the term.
+    terms: HashMap<String, Vec<(u32, Vec<u32>)>>,
+    /// The id and timestamp of each document.
+    posts: Vec<(u64, i64)>,
+}
+
+impl<'t> IndexBuilder<'t> {
+    pub fn new(thulac: &'t Thulac) -> Self {
+        Self {
+            thulac,
+            analyzer: Analyzer::new(),
+            postings: Postings::default(),
+        }
+    }
+    /// Sets the analyzer, which must also be used for queries.
+    pub fn with_analyzer(mut self, analyzer: Analyzer) -> Self {
+        self.analyzer = analyzer;
+        self
+    }
+    pub fn analyzer(&self) -> &Analyzer {
+        &self.analyzer
+    }
+    /// Number of posts added.
+    pub fn len(&self) -> usize {
+        self.postings.posts.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.postings.posts.is_empty()
+    }
+    /// Adds a post as the next document, with its text and replies.
+    pub fn add(&mut self, post: &Post) -> Result<()> {
+        let preprocesses = iter::once(&post.text)
+            .chain(post.replies.iter())
+            .map(|text| self.thulac.preprocess(text))
+            .collect::<Vec<_>>();
+        let fields = preprocesses
+            .iter()
+            .map(|x| self.thulac.try_cut(x))
+            .collect::<Result<Vec<_>>>()?;
+        let terms = self.analyzer.document(&fields);
+        self.postings.add(post.id, post.timestamp, terms);
+        Ok(())
+    }
+    /// Writes the index to the directory `dir`, creating it if needed.
+    pub fn write<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        self.postings.write(dir.as_ref())
+    }
+}
+
+impl Postings {
+    /// Adds a document with the positions of each of its terms.
+    fn add(&mut self, id: u64, timestamp: i64, terms: HashMap<String, Vec<u32>>) {
+        let doc = self.posts.len() as u32;
+        self.posts.push((id, timestamp));
+        for (term, positions) in terms {
+            self.terms.entry(term).or_default().push((doc, positions));
+        }
+    }
+    fn write(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let mut terms = self.terms.keys().collect::<Vec<_>>();
+        terms.sort_unstable();
+        let mut offsets = Vec::with_capacity(terms.len() + 1);
+        let mut postings = Vec::new();
+        for term in terms.iter() {
+            offsets.push(postings.len() as u64);
+            let documents = &self.terms[*term];
+            varint::write(&mut postings, documents.len() as u64);
+            let mut last_doc = 0;
+            for (doc, positions) in documents {
+                varint::write(&mut postings, u64::from(doc - last_doc));
+                varint::write(&mut postings, positions.len() as u64);
+                let mut last_position = 0;
+                for position in positions {
+                    varint::write(&mut postings, u64::from(position - last_position));
+                    last_position = *position;
+                }
+                last_doc = *doc;
+            }
+        }
+        offsets.push(postings.len() as u64);
+
+        let keys = terms
+            .iter()
+            .map(|x| x.to_string() + "\0")
+            .collect::<Vec<_>>();
+        let dat = Dat::build(
+            keys.iter()
+                .enumerate()
+                .map(|(i, x)| (&x[..], i as i32))
+                .collect(),
+        );
+        let mut writer = BufWriter::new(File::create(dir.join(TERMS))?);
+        dat.save(&mut writer)?;
+        writer.flush()?;
+        let mut writer = BufWriter::new(File::create(dir.join(OFFSETS))?);
+        for offset in offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        writer.flush()?;
+        fs::write(dir.join(POSTINGS), postings)?;
+        let mut writer = BufWriter::new(File::create(dir.join(POSTS))?);
+        for (id, timestamp) in self.posts.iter() {
+            writer.write_all(&id.to_le_bytes())?;
+            writer.write_all(&timestamp.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Index, Posting, QueryWord};
+
+    fn terms(positions: &[(&str, &[u32])]) -> HashMap<String, Vec<u32>> {
+        positions
+            .iter()
+            .map(|(term, positions)| (term.to_string(), positions.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_write() {
+        let mut postings = Postings::default();
+        postings.add(
+            7,
+            100,
+            terms(&[("树洞", &[0, 300]), ("洞", &[0]), ("好玩", &[1])]),
+        );
+        postings.add(9, 200, terms(&[("好玩", &[0]), ("树洞", &[1])]));
+        postings.add(12, 300, terms(&[("树洞", &[2])]));
+        let dir = std::env::temp_dir().join(format!("thuhole-index-{}", std::process::id()));
+        postings.write(&dir).unwrap();
+        let index = Index::open(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.post(1), (9, 200));
+        assert_eq!(
+            index.postings("树洞").unwrap(),
+            [
+                Posting {
+                    doc: 0,
+                    positions: vec![0, 300]
+                },
+                Posting {
+                    doc: 1,
+                    positions: vec![1]
+                },
+                Posting {
+                    doc: 2,
+                    positions: vec![2]
+                },
+            ]
+        );
+        assert!(index.postings("树").unwrap().is_empty());
+        assert!(index.postings("未知").unwrap().is_empty());
+        let search = |words: &[&str], phrase| {
+            let words = words.iter().map(|x| QueryWord::term(x)).collect::<Vec<_>>();
+            index.search(&words, phrase).unwrap()
+        };
+        assert_eq!(search(&["树洞", "好玩"], false), [0, 1]);
+        assert_eq!(search(&["树洞", "好玩"], true), [0]);
+        assert_eq!(search(&["好玩", "树洞"], true), [1]);
+        assert!(search(&["树洞", "未知"], false).is_empty());
+        let word = |alternatives: &[&[&str]]| QueryWord {
+            alternatives: alternatives
+                .iter()
+                .map(|x| x.iter().map(|x| x.to_string()).collect())
+                .collect(),
+        };
+        // either alternative, or all terms of one at the same position
+        let either = word(&[&["未知"], &["好玩"]]);
+        assert_eq!(index.search(&[either], false).unwrap(), [0, 1]);
+        let both = word(&[&["树洞", "洞"]]);
+        assert_eq!(
+            index.search(std::slice::from_ref(&both), false).unwrap(),
+            [0]
+        );
+        assert_eq!(
+            index.search(&[both, word(&[&["好玩"]])], true).unwrap(),
+            [0]
+        );
+        let apart = word(&[&["洞", "好玩"]]);
+        assert!(index.search(&[apart], false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let mut postings = Postings::default();
+        postings.add(7, 100, terms(&[("好玩", &[0]), ("树洞", &[1])]));
+        let dir = std::env::temp_dir().join(format!("thuhole-corrupt-{}", std::process::id()));
+        postings.write(&dir).unwrap();
+        // the postings of the first term end past those of the index
+        let mut offsets = fs::read(dir.join(OFFSETS)).unwrap();
+        offsets[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(dir.join(OFFSETS), offsets).unwrap();
+        let index = Index::open(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(index.postings("好玩").is_err());
+        assert!(index.postings("树洞").is_err());
+    }
+}
@@ -0,0 +1,51 @@
+//! An on-disk inverted index of tree-hole posts, tokenized by [`Thulac`].
+//!
+//! An index is a directory of four files:
+//!
+//! - `terms.dat`, a [`Dat`](thulac_rs::Dat) mapping each term followed by
+//!   `\0` to its number;
+//! - `offsets.bin`, the byte offset in `postings.bin` of the postings of
+//!   each term, then the length of `postings.bin`, as little-endian `u64`;
+//! - `postings.bin`, the postings of each term: the number of documents,
+//!   then for each document the difference with the previous document, the
+//!   number of positions, and the differences between positions, all as
+//!   varints;
+//! - `posts.bin`, the id and timestamp of each document, as little-endian
+//!   `u64` and `i64`.
+
+mod analyzer;
+mod builder;
+mod reader;
+mod varint;
+
+use serde::Deserialize;
+use thulac_rs::{Result, Thulac};
+
+pub use analyzer::{Analyzer, QueryWord};
+pub use builder::IndexBuilder;
+pub use reader::{Index, Posting};
+
+const TERMS: &str = "terms.dat";
+const OFFSETS: &str = "offsets.bin";
+const POSTINGS: &str = "postings.bin";
+const POSTS: &str = "posts.bin";
+
+/// Positions skipped between the text of a post and each reply, so phrases
+/// do not span them.
+const FIELD_GAP: u32 = 16;
+
+/// A post to index, as read from a line of JSON.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Post {
+    pub id: u64,
+    pub timestamp: i64,
+    pub text: String,
+    #[serde(default)]
+    pub replies: Vec<String>,
+}
+
+/// The words of `query`, turned into terms by `analyzer` as posts are.
+pub fn query(thulac: &Thulac, analyzer: &Analyzer, query: &str) -> Result<Vec<QueryWord>> {
+    let preprocess = thulac.preprocess(query);
+    Ok(analyzer.query(&thulac.try_cut(&preprocess)?))
+}
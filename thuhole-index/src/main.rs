@@ -0,0 +1,161 @@
+use std::{
+    fs::File,
+    io::{stdout, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::{Args, Parser, Subcommand};
+use thuhole_index::{query, Analyzer, Index, IndexBuilder, Post};
+use thulac_rs::{PatternRecognizer, Pinyin, PostProcessor, RuleSet, Thulac};
+
+#[derive(Parser)]
+#[command(about = "Inverted index of tree-hole posts")]
+struct Cli {
+    /// Directory containing the thulac model
+    #[arg(short, long, env = "THULAC_MODEL_PATH", global = true)]
+    model: Option<PathBuf>,
+    /// User dictionary of `word[<TAB>tag[<TAB>priority]]` lines; words
+    /// without a tag are tagged `uw`; may be given several times
+    #[arg(short, long = "user-dict", value_name = "PATH", global = true)]
+    user_dicts: Vec<PathBuf>,
+    /// File of `pattern<TAB>action` rules merging, splitting or retagging
+    /// words after user dictionaries; may be given several times
+    #[arg(long, value_name = "PATH", global = true)]
+    rules: Vec<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Index posts read as lines of JSON with `id`, `timestamp`, `text` and
+    /// optional `replies`
+    Build(BuildArgs),
+    /// Print the `id<TAB>timestamp` of the posts containing all words of the
+    /// query, newest first
+    Search(SearchArgs),
+}
+
+#[derive(Args)]
+struct BuildArgs {
+    /// File of posts, one JSON object per line
+    #[arg(short, long)]
+    input: PathBuf,
+    /// Directory to write the index to
+    #[arg(short, long)]
+    output: PathBuf,
+    #[command(flatten)]
+    analyzer: AnalyzerArgs,
+}
+
+/// How posts and queries are turned into terms; searches must use the
+/// options the index was built with.
+#[derive(Args)]
+struct AnalyzerArgs {
+    /// Pinyin dictionary of `word<TAB>syllables` lines, also indexing the
+    /// pinyin and initials of each word
+    #[arg(long, value_name = "PATH")]
+    pinyin: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct SearchArgs {
+    /// Directory of the index
+    #[arg(long)]
+    index: PathBuf,
+    #[command(flatten)]
+    analyzer: AnalyzerArgs,
+    /// Only match posts containing the words of the query in order
+    #[arg(long)]
+    phrase: bool,
+    /// Maximum number of posts printed
+    #[arg(long)]
+    top: Option<usize>,
+    /// Words of the query, joined by spaces
+    #[arg(required = true)]
+    query: Vec<String>,
+}
+
+fn analyzer(args: AnalyzerArgs) -> Analyzer {
+    let mut analyzer = Analyzer::new();
+    if let Some(path) = args.pinyin {
+        let pinyin = File::open(&path)
+            .and_then(|x| Pinyin::load_txt(&mut BufReader::new(x)))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+        analyzer = analyzer.with_pinyin(pinyin);
+    }
+    analyzer
+}
+
+fn build(thulac: &Thulac, args: BuildArgs) {
+    let mut builder = IndexBuilder::new(thulac).with_analyzer(analyzer(args.analyzer));
+    let input = File::open(&args.input)
+        .unwrap_or_else(|e| panic!("failed to open {}: {}", args.input.display(), e));
+    for (i, line) in BufReader::new(input).lines().enumerate() {
+        let line = line.expect("failed to read input");
+        if line.trim().is_empty() {
+            continue;
+        }
+        let post: Post = serde_json::from_str(&line)
+            .unwrap_or_else(|e| panic!("invalid post on line {}: {}", i + 1, e));
+        builder.add(&post).expect("failed to segment post");
+    }
+    builder
+        .write(&args.output)
+        .unwrap_or_else(|e| panic!("failed to write {}: {}", args.output.display(), e));
+    eprintln!("indexed {} posts", builder.len());
+}
+
+fn search(thulac: &Thulac, args: SearchArgs) {
+    let index = Index::open(&args.index)
+        .unwrap_or_else(|e| panic!("failed to open {}: {}", args.index.display(), e));
+    let analyzer = analyzer(args.analyzer);
+    let words = query(thulac, &analyzer, &args.query.join(" ")).expect("failed to segment query");
+    let mut posts = index
+        .search(&words, args.phrase)
+        .expect("failed to read index")
+        .into_iter()
+        .map(|x| index.post(x))
+        .collect::<Vec<_>>();
+    posts.sort_unstable_by_key(|&(id, timestamp)| (std::cmp::Reverse(timestamp), id));
+    posts.truncate(args.top.unwrap_or(usize::MAX));
+    let mut writer = BufWriter::new(stdout().lock());
+    for (id, timestamp) in posts {
+        writeln!(writer, "{}\t{}", id, timestamp).expect("failed to write output");
+    }
+    writer.flush().expect("failed to write output");
+}
+
+/// Loads the model with the dictionaries given. Post references like
+/// `#123456` are always recognized, so posts referencing a post can be found.
+fn load(cli: &Cli) -> Thulac {
+    let path = cli
+        .model
+        .as_ref()
+        .expect("--model or env THULAC_MODEL_PATH is required");
+    let mut thulac = Thulac::load(path).expect("failed to load model");
+    for path in cli.user_dicts.iter() {
+        let post = File::open(path)
+            .and_then(|x| PostProcessor::load_txt(&mut BufReader::new(x), "uw".into()))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+        thulac.add_postprocessor(post);
+    }
+    for path in cli.rules.iter() {
+        let rules = File::open(path)
+            .map_err(Into::into)
+            .and_then(|x| RuleSet::load_txt(&mut BufReader::new(x)))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+        thulac.add_rules(rules);
+    }
+    thulac.add_recognizer(Box::new(PatternRecognizer::hole()));
+    thulac
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let thulac = load(&cli);
+    match cli.command {
+        Command::Build(args) => build(&thulac, args),
+        Command::Search(args) => search(&thulac, args),
+    }
+}
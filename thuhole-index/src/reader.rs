@@ -0,0 +1,201 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+use thulac_rs::{Dat, Result};
+
+use crate::{varint, QueryWord, OFFSETS, POSTINGS, POSTS, TERMS};
+
+/// A document containing a term, with the positions of the term in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Posting {
+    pub doc: u32,
+    pub positions: Vec<u32>,
+}
+
+/// An index written by [`crate::IndexBuilder::write`], read into memory.
+pub struct Index {
+    terms: Dat,
+    offsets: Vec<u64>,
+    postings: Vec<u8>,
+    posts: Vec<(u64, i64)>,
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, reason)
+}
+
+/// Adds a difference read from the postings to a document or position.
+fn add_delta(value: u32, delta: u64) -> Result<u32> {
+    u32::try_from(delta)
+        .ok()
+        .and_then(|x| value.checked_add(x))
+        .ok_or_else(|| invalid("document or position out of range").into())
+}
+
+impl Index {
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        let terms = Dat::load(&mut File::open(dir.join(TERMS))?)?;
+        let offsets = fs::read(dir.join(OFFSETS))?;
+        if offsets.len() % 8 != 0 {
+            return Err(invalid("file size unexpected").into());
+        }
+        let offsets = offsets
+            .chunks_exact(8)
+            .map(|x| u64::from_le_bytes(x.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        let postings = fs::read(dir.join(POSTINGS))?;
+        if offsets.last() != Some(&(postings.len() as u64)) {
+            return Err(invalid("offsets do not match postings").into());
+        }
+        let posts = fs::read(dir.join(POSTS))?;
+        if posts.len() % 16 != 0 {
+            return Err(invalid("file size unexpected").into());
+        }
+        let posts = posts
+            .chunks_exact(16)
+            .map(|x| {
+                (
+                    u64::from_le_bytes(x[..8].try_into().unwrap()),
+                    i64::from_le_bytes(x[8..].try_into().unwrap()),
+                )
+            })
+            .collect();
+        Ok(Self {
+            terms,
+            offsets,
+            postings,
+            posts,
+        })
+    }
+    /// Number of documents.
+    pub fn len(&self) -> usize {
+        self.posts.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.posts.is_empty()
+    }
+    /// The id and timestamp of the post of document `doc`.
+    pub fn post(&self, doc: u32) -> (u64, i64) {
+        self.posts[doc as usize]
+    }
+    /// The documents containing `term`, in order, or none for an unknown
+    /// term.
+    pub fn postings(&self, term: &str) -> Result<Vec<Posting>> {
+        let Some(leaf) = self
+            .terms
+            .descendant(self.terms.root(), term)
+            .and_then(|x| self.terms.child(x, '\0'))
+        else {
+            return Ok(Vec::new());
+        };
+        let index = self.terms.base(leaf) as usize;
+        let (start, end) = match (self.offsets.get(index), self.offsets.get(index + 1)) {
+            (Some(&start), Some(&end)) if start <= end => (start as usize, end as usize),
+            _ => return Err(invalid("term out of range").into()),
+        };
+        let buffer = self
+            .postings
+            .get(start..end)
+            .ok_or_else(|| invalid("term out of range"))?;
+        let mut offset = 0;
+        let count = varint::read(buffer, &mut offset)?;
+        let mut postings = Vec::new();
+        let mut doc = 0;
+        for _ in 0..count {
+            doc = add_delta(doc, varint::read(buffer, &mut offset)?)?;
+            let mut position = 0;
+            let positions = (0..varint::read(buffer, &mut offset)?)
+                .map(|_| {
+                    position = add_delta(position, varint::read(buffer, &mut offset)?)?;
+                    Ok(position)
+                })
+                .collect::<Result<_>>()?;
+            postings.push(Posting { doc, positions });
+        }
+        if doc as usize >= self.posts.len() && count != 0 {
+            return Err(invalid("document out of range").into());
+        }
+        Ok(postings)
+    }
+    /// The documents containing all of `terms` at the same positions.
+    fn postings_all(&self, terms: &[String]) -> Result<Vec<Posting>> {
+        let Some((first, rest)) = terms.split_first() else {
+            return Ok(Vec::new());
+        };
+        let mut postings = self.postings(first)?;
+        for term in rest {
+            let other = self.postings(term)?;
+            postings.retain_mut(|posting| {
+                let Ok(i) = other.binary_search_by_key(&posting.doc, |x| x.doc) else {
+                    return false;
+                };
+                let positions = &other[i].positions;
+                posting
+                    .positions
+                    .retain(|x| positions.binary_search(x).is_ok());
+                !posting.positions.is_empty()
+            });
+        }
+        Ok(postings)
+    }
+    /// The documents matching `word`, with the positions where it matches.
+    fn matches(&self, word: &QueryWord) -> Result<Vec<Posting>> {
+        let mut docs = BTreeMap::<u32, Vec<u32>>::new();
+        for alternative in word.alternatives.iter() {
+            for posting in self.postings_all(alternative)? {
+                docs.entry(posting.doc)
+                    .or_default()
+                    .extend(posting.positions);
+            }
+        }
+        Ok(docs
+            .into_iter()
+            .map(|(doc, mut positions)| {
+                positions.sort_unstable();
+                positions.dedup();
+                Posting { doc, positions }
+            })
+            .collect())
+    }
+    /// The documents matching all of `words`, in order. With `phrase`, the
+    /// words must follow each other in this order.
+    pub fn search(&self, words: &[QueryWord], phrase: bool) -> Result<Vec<u32>> {
+        let Some((first, rest)) = words.split_first() else {
+            return Ok(Vec::new());
+        };
+        let rest = rest
+            .iter()
+            .map(|x| self.matches(x))
+            .collect::<Result<Vec<_>>>()?;
+        let mut docs = Vec::new();
+        for posting in self.matches(first)? {
+            let others = rest
+                .iter()
+                .map(|x| {
+                    x.binary_search_by_key(&posting.doc, |x| x.doc)
+                        .ok()
+                        .map(|i| &x[i].positions)
+                })
+                .collect::<Option<Vec<_>>>();
+            let Some(others) = others else {
+                continue;
+            };
+            let found = !phrase
+                || posting.positions.iter().any(|&start| {
+                    others
+                        .iter()
+                        .zip(1..)
+                        .all(|(positions, i)| positions.binary_search(&(start + i)).is_ok())
+                });
+            if found {
+                docs.push(posting.doc);
+            }
+        }
+        Ok(docs)
+    }
+}
@@ -0,0 +1,49 @@
+use std::io::{Error, Result};
+
+/// Appends `value` as a LEB128 varint: 7 bits per byte, low bits first,
+/// with the high bit set on all bytes but the last.
+pub fn write(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+/// Reads a varint written by [`write`] at `*offset`, moving past it.
+pub fn read(buffer: &[u8], offset: &mut usize) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *buffer
+            .get(*offset)
+            .ok_or_else(|| Error::other("truncated varint"))?;
+        *offset += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err(Error::other("varint too long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint() {
+        let values = [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+        let mut buffer = Vec::new();
+        for value in values {
+            write(&mut buffer, value);
+        }
+        assert_eq!(buffer[..5], [0, 1, 127, 0x80, 1]);
+        let mut offset = 0;
+        for value in values {
+            assert_eq!(read(&buffer, &mut offset).unwrap(), value);
+        }
+        assert_eq!(offset, buffer.len());
+        assert!(read(&buffer, &mut offset).is_err());
+        assert!(read(&[0x80], &mut 0).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use std::{env, thread};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rayon::ThreadPoolBuilder;
+use thulac_rs::Thulac;
+
+const SENTENCES: [&str; 4] = [
+    "今天在紫荆园吃到了很好吃的麻辣香锅，推荐给大家。",
+    "有没有人知道清华大学计算机系的选课系统什么时候开放？",
+    "洞主你好，我也遇到过类似的情况，建议直接联系辅导员。",
+    "Python和C++哪个更适合作为第一门编程语言？",
+];
+
+fn cut_batch(c: &mut Criterion) {
+    let thulac =
+        Thulac::load(env::var("THULAC_MODEL_PATH").expect("failed to fetch env THULAC_MODEL_PATH"))
+            .expect("failed to load model");
+    let docs = (0..1024)
+        .map(|i| SENTENCES[i % SENTENCES.len()].repeat(1 + i % 8))
+        .collect::<Vec<_>>();
+    let docs = docs.iter().map(String::as_str).collect::<Vec<_>>();
+    let bytes = docs.iter().map(|x| x.len() as u64).sum();
+    let max_threads = thread::available_parallelism().map_or(1, |x| x.get());
+
+    let mut group = c.benchmark_group("cut_batch");
+    group.throughput(Throughput::Bytes(bytes));
+    group.sample_size(10);
+    let mut threads = 1;
+    while threads <= max_threads {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &docs, |b, docs| {
+            b.iter(|| pool.install(|| thulac.cut_batch(docs)))
+        });
+        threads *= 2;
+    }
+    group.finish();
+}
+
+criterion_group!(benches, cut_batch);
+criterion_main!(benches);
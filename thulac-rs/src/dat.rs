@@ -1,5 +1,5 @@
 use std::{
-    io::{BufRead, Error, ErrorKind, Read, Result, Seek, Write},
+    io::{BufRead, Error, Read, Result, Seek, Write},
     iter::Peekable,
     mem::size_of,
     slice,
@@ -38,8 +38,8 @@ pub struct Dat {
 impl Dat {
     pub fn load<R: Read + Seek>(reader: &mut R) -> Result<Self> {
         let len = reader.stream_len()? as usize;
-        if len % size_of::<DatEntry>() != 0 {
-            return Err(Error::new(ErrorKind::Other, "file size unexpected"));
+        if !len.is_multiple_of(size_of::<DatEntry>()) {
+            return Err(Error::other("file size unexpected"));
         }
         let mut entries = vec![DatEntry::default(); len / size_of::<DatEntry>()];
         reader.read_exact(unsafe {
@@ -56,8 +56,19 @@ impl Dat {
         })?;
         Ok(())
     }
+    /// Loads a set of one word per line. Words are trimmed, and empty lines
+    /// and repeated words are skipped.
     pub fn load_set_txt<R: BufRead>(reader: &mut R, insert_end: bool) -> Result<Self> {
-        let mut words = reader.lines().collect::<Result<Vec<_>>>()?;
+        let mut words = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let word = line.trim();
+            if !word.is_empty() {
+                words.push(word.to_owned());
+            }
+        }
+        words.sort_unstable();
+        words.dedup();
         if insert_end {
             words.iter_mut().for_each(|x| x.push('\0'));
         }
@@ -71,7 +82,7 @@ impl Dat {
                 x.and_then(|x| {
                     x.rfind('\t')
                         .map(move |i| (x, i))
-                        .ok_or_else(|| Error::new(ErrorKind::Other, "missing delimiter"))
+                        .ok_or_else(|| Error::other("missing delimiter"))
                 })
                 .and_then(|(mut x, i)| {
                     x[i + 1..]
@@ -80,7 +91,7 @@ impl Dat {
                             x.drain(i..);
                             (x, v)
                         })
-                        .map_err(|_| Error::new(ErrorKind::Other, "invalid value of entry"))
+                        .map_err(|_| Error::other("invalid value of entry"))
                 })
             })
             .collect::<Result<Vec<_>>>()?;
@@ -105,9 +116,13 @@ impl Dat {
         parent.and_then(move |x| self.child(x, offset))
     }
     pub fn descendant(&self, parent: i32, offset: &str) -> Option<i32> {
-        offset
-            .chars()
-            .fold(Some(parent), |p, ch| self.and_child(p, ch))
+        offset.chars().try_fold(parent, |p, ch| self.child(p, ch))
+    }
+    /// Whether `word` is an entry of a set loaded with `insert_end`.
+    pub fn contains(&self, word: &str) -> bool {
+        self.descendant(self.root(), word)
+            .and_then(|x| self.child(x, '\0'))
+            .is_some()
     }
     pub fn base(&self, node: i32) -> i32 {
         self.entries[node as usize].base
@@ -148,8 +163,8 @@ impl Dat {
             let base_offset = last_offset;
             let mut offsets = Vec::new();
             let mut values = Vec::new();
-            for i in 0..map.len() {
-                let ch = map[i].0.next().unwrap() as i32;
+            for (i, entry) in map.iter_mut().enumerate() {
+                let ch = entry.0.next().unwrap() as i32;
                 if ch != last_offset {
                     offsets.push(last_offset - base_offset);
                     values.push(last_start..i);
@@ -160,7 +175,7 @@ impl Dat {
             offsets.push(last_offset - base_offset);
             values.push(last_start..map.len());
             let base = builder.alloc(&offsets);
-            for (offset, value) in offsets.into_iter().zip(values.into_iter()) {
+            for (offset, value) in offsets.into_iter().zip(values) {
                 let index = base + offset;
                 let base = process(builder, &mut map[value], index);
                 builder.set(index, DatEntry { base, check });
@@ -169,6 +184,9 @@ impl Dat {
         }
 
         let mut builder = DatBuilder::new();
+        if map.is_empty() {
+            return builder.cleanup();
+        }
         let mut map = map
             .into_iter()
             .map(|(string, value)| (string.chars().peekable(), value))
@@ -235,7 +253,7 @@ impl DatBuilder {
     fn alloc(&mut self, offsets: &[i32]) -> i32 {
         let sentinel = self.sentinel();
         let mut base = self.dat[sentinel as usize].next();
-        'outer: while base != sentinel as i32 {
+        'outer: while base != sentinel {
             for offset in offsets {
                 let offset = (base + offset) as usize;
                 if offset >= self.dat.len() {
@@ -248,7 +266,7 @@ impl DatBuilder {
             }
             break;
         }
-        if base == sentinel as i32 {
+        if base == sentinel {
             self.extend();
             base = self.dat[self.sentinel() as usize].next();
         }
@@ -302,5 +320,15 @@ mod tests {
         );
         assert_eq!(dat.descendant(dat.root(), "hix").map(|x| dat.base(x)), None);
         assert_eq!(dat.descendant(dat.root(), "x").map(|x| dat.base(x)), None);
+        let empty = Dat::build(Vec::new());
+        assert!(!empty.contains("x"));
+    }
+
+    #[test]
+    fn test_load_set_txt() {
+        let dat = Dat::load_set_txt(&mut "的\n了\n\n\n 的 \n".as_bytes(), true).unwrap();
+        assert!(dat.contains("的"));
+        assert!(dat.contains("了"));
+        assert!(!dat.contains(""));
     }
 }
@@ -0,0 +1,191 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use regex::Regex;
+
+/// Occurrences of an n-gram and of the chars around it.
+#[derive(Default)]
+struct Gram {
+    count: usize,
+    left: HashMap<char, usize>,
+    right: HashMap<char, usize>,
+    /// Occurrences at the start of a run of Chinese chars, each counted as
+    /// a different left neighbor.
+    left_ends: usize,
+    /// Occurrences at the end of a run of Chinese chars.
+    right_ends: usize,
+}
+
+/// Branching entropy of the neighbors of an n-gram, in nats.
+fn entropy(neighbors: &HashMap<char, usize>, ends: usize) -> f64 {
+    let total = (neighbors.values().sum::<usize>() + ends) as f64;
+    let entropy = neighbors
+        .values()
+        .map(|&x| {
+            let p = x as f64 / total;
+            -p * p.ln()
+        })
+        .sum::<f64>();
+    entropy + ends as f64 * total.ln() / total
+}
+
+/// A word found by [`Discovery::candidates`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub word: String,
+    pub count: usize,
+    /// The lowest pointwise mutual information of the two parts of the word
+    /// over all the ways to cut it in two, high when its chars stick
+    /// together.
+    pub pmi: f64,
+    /// Branching entropy of the chars before the word, high when it is used
+    /// in many contexts.
+    pub left_entropy: f64,
+    pub right_entropy: f64,
+}
+
+/// Finds words missing from dictionaries in an unlabeled corpus, such as
+/// new slang, among the n-grams of Chinese chars that occur often, whose
+/// chars stick together and which are used in varied contexts.
+pub struct Discovery {
+    han: Regex,
+    max_len: usize,
+    min_count: usize,
+    min_pmi: f64,
+    min_entropy: f64,
+    /// Number of Chinese chars read.
+    chars: usize,
+    grams: HashMap<String, Gram>,
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Discovery {
+    /// Looks for words of 2 to 4 chars occurring at least 5 times, with a
+    /// PMI of at least 3 and a branching entropy of at least 1 on both
+    /// sides.
+    pub fn new() -> Self {
+        Self {
+            han: Regex::new(r"\p{Han}+").unwrap(),
+            max_len: 4,
+            min_count: 5,
+            min_pmi: 3.0,
+            min_entropy: 1.0,
+            chars: 0,
+            grams: HashMap::new(),
+        }
+    }
+    /// Sets the maximum number of chars of a word. Counts grow quickly with
+    /// it, so it should be set before reading the corpus.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.max(2);
+        self
+    }
+    pub fn with_min_count(mut self, min_count: usize) -> Self {
+        self.min_count = min_count;
+        self
+    }
+    pub fn with_min_pmi(mut self, min_pmi: f64) -> Self {
+        self.min_pmi = min_pmi;
+        self
+    }
+    pub fn with_min_entropy(mut self, min_entropy: f64) -> Self {
+        self.min_entropy = min_entropy;
+        self
+    }
+    /// Counts the n-grams of a text. Chars other than Chinese ones, such as
+    /// punctuation, separate words.
+    pub fn add_text(&mut self, text: &str) {
+        for run in self.han.find_iter(text) {
+            let chars = run.as_str().char_indices().collect::<Vec<_>>();
+            let run = run.as_str();
+            self.chars += chars.len();
+            for start in 0..chars.len() {
+                for end in start + 1..=(start + self.max_len).min(chars.len()) {
+                    let to = chars.get(end).map_or(run.len(), |x| x.0);
+                    let gram = self
+                        .grams
+                        .entry(run[chars[start].0..to].to_owned())
+                        .or_default();
+                    gram.count += 1;
+                    match start.checked_sub(1) {
+                        Some(i) => *gram.left.entry(chars[i].1).or_default() += 1,
+                        None => gram.left_ends += 1,
+                    }
+                    match chars.get(end) {
+                        Some(x) => *gram.right.entry(x.1).or_default() += 1,
+                        None => gram.right_ends += 1,
+                    }
+                }
+            }
+        }
+    }
+    fn count(&self, gram: &str) -> usize {
+        self.grams.get(gram).map_or(0, |x| x.count)
+    }
+    /// The words meeting the thresholds, by decreasing count, then by word.
+    pub fn candidates(&self) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+        for (word, gram) in self.grams.iter() {
+            if gram.count < self.min_count || word.chars().nth(1).is_none() {
+                continue;
+            }
+            let pmi = word
+                .char_indices()
+                .skip(1)
+                .map(|(i, _)| {
+                    let parts = self.count(&word[..i]) * self.count(&word[i..]);
+                    (gram.count as f64 * self.chars as f64 / parts as f64).ln()
+                })
+                .fold(f64::INFINITY, f64::min);
+            let left_entropy = entropy(&gram.left, gram.left_ends);
+            let right_entropy = entropy(&gram.right, gram.right_ends);
+            if pmi >= self.min_pmi && left_entropy.min(right_entropy) >= self.min_entropy {
+                candidates.push(Candidate {
+                    word: word.clone(),
+                    count: gram.count,
+                    pmi,
+                    left_entropy,
+                    right_entropy,
+                });
+            }
+        }
+        candidates.sort_by(|a, b| (Reverse(a.count), &a.word).cmp(&(Reverse(b.count), &b.word)));
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let mut discovery = Discovery::new().with_min_count(3).with_min_pmi(1.0);
+        for text in [
+            "今天好绝绝子啊",
+            "这也太绝绝子了",
+            "真是绝绝子！",
+            "我觉得绝绝子呢",
+            "你看，绝绝子",
+            "我们去吃饭",
+            "他们去吃面",
+            "你去吃吧",
+            "谁去吃呢",
+            "一起去吃",
+        ] {
+            discovery.add_text(text);
+        }
+        let candidates = discovery.candidates();
+        let words = candidates.iter().map(|x| &x.word[..]).collect::<Vec<_>>();
+        assert!(words.contains(&"绝绝子"));
+        assert!(!words.contains(&"绝绝"));
+        assert!(!words.contains(&"绝子"));
+        let candidate = &candidates[words.iter().position(|&x| x == "绝绝子").unwrap()];
+        assert_eq!(candidate.count, 5);
+        assert!(candidate.left_entropy > 1.0 && candidate.right_entropy > 1.0);
+    }
+}
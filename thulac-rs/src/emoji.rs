@@ -0,0 +1,139 @@
+use std::ops::Range;
+
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{recognize::glued_to_ascii, Recognizer};
+
+/// Kaomoji in brackets, like `(´･ω･`)` or `o(╥﹏╥)o`, checked against
+/// [`FACE`] afterwards.
+const BRACKETED: &str = r"[oOヽ╮\\]?[(（][^()（）\s\p{Han}]{1,10}[)）][oOﾉノ╭/]?";
+/// Eyes or mouths of which a bracketed kaomoji must contain at least one, so
+/// that `(1)`, `(30°)` or `f(x^2)` are not taken for faces. ASCII chars only
+/// count in pairs of eyes like `^_^` or `;_;`.
+const FACE: &str = r"[´･・ωДд▽≧≦＾ﾟ◕‿╥﹏╯╰□≖ಠ∀ε〃＿｀]|\^[_\-ωo]?\^|;_;";
+/// Kaomoji without brackets.
+const BARE: &str = r"\^_?\^|T[_A]T|Q[Aw]Q|>_<|-_-|=_=|[oO]rz|OTZ|_\(:з」∠\)_";
+
+/// Recognizes emoji, tagged `x`, and kaomoji, tagged `e`. Emoji are found
+/// per extended grapheme cluster, so ZWJ sequences, skin tones, keycaps and
+/// flags stay whole.
+pub struct EmojiRecognizer {
+    pictographic: Regex,
+    presentation: Regex,
+    bracketed: Regex,
+    face: Regex,
+    bare: Regex,
+}
+
+impl EmojiRecognizer {
+    pub fn new() -> Self {
+        Self {
+            pictographic: Regex::new(r"[\p{Extended_Pictographic}\p{Regional_Indicator}\u{20E3}]")
+                .unwrap(),
+            presentation: Regex::new(r"^\p{Emoji_Presentation}$").unwrap(),
+            bracketed: Regex::new(BRACKETED).unwrap(),
+            face: Regex::new(FACE).unwrap(),
+            bare: Regex::new(BARE).unwrap(),
+        }
+    }
+}
+
+impl Default for EmojiRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drops the `o` or `O` arms of a bracketed kaomoji glued to a word, as in
+/// `Hello(^_^)`.
+fn arms_unglued(raw: &str, mut range: Range<usize>) -> Range<usize> {
+    if raw[range.clone()].starts_with(['o', 'O']) && glued_to_ascii(raw, &range) {
+        range.start += 1;
+    }
+    if raw[range.clone()].ends_with(['o', 'O']) && glued_to_ascii(raw, &range) {
+        range.end -= 1;
+    }
+    range
+}
+
+impl Recognizer for EmojiRecognizer {
+    fn recognize<'a>(&'a self, raw: &str, spans: &mut Vec<(Range<usize>, &'a str)>) {
+        spans.extend(
+            raw.grapheme_indices(true)
+                .filter(|(_, grapheme)| {
+                    // a lone char like `©` or `1` is an emoji only in emoji presentation
+                    if grapheme.chars().nth(1).is_some() {
+                        self.pictographic.is_match(grapheme)
+                    } else {
+                        self.presentation.is_match(grapheme)
+                    }
+                })
+                .map(|(i, grapheme)| (i..i + grapheme.len(), "x")),
+        );
+        spans.extend(
+            self.bracketed
+                .find_iter(raw)
+                .map(|x| arms_unglued(raw, x.range()))
+                .filter(|x| self.face.is_match(&raw[x.clone()]) && !glued_to_ascii(raw, x))
+                .map(|x| (x, "e")),
+        );
+        spans.extend(
+            self.bare
+                .find_iter(raw)
+                .map(|x| x.range())
+                .filter(|x| !glued_to_ascii(raw, x))
+                .map(|x| (x, "e")),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recognize::resolve;
+
+    #[test]
+    fn test_emoji() {
+        let emoji = EmojiRecognizer::new();
+        let raw = "笑死😂👍🏽👨‍👩‍👧🇨🇳⭐(´･ω･`)好的^_^，见(1)，Borz©";
+        let mut spans = Vec::new();
+        emoji.recognize(raw, &mut spans);
+        let spans = resolve(raw, spans)
+            .into_iter()
+            .map(|(range, tag)| (&raw[range], tag))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                ("😂", "x"),
+                ("👍🏽", "x"),
+                ("👨‍👩‍👧", "x"),
+                ("🇨🇳", "x"),
+                ("⭐", "x"),
+                ("(´･ω･`)", "e"),
+                ("^_^", "e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_kaomoji_negatives() {
+        let emoji = EmojiRecognizer::new();
+        let kaomoji = |raw: &str| {
+            let mut spans = Vec::new();
+            emoji.recognize(raw, &mut spans);
+            resolve(raw, spans)
+                .into_iter()
+                .map(|(range, _)| raw[range].to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kaomoji("Hello(^_^)"), ["(^_^)"]);
+        assert_eq!(kaomoji("(^_^)okay"), ["(^_^)"]);
+        assert_eq!(kaomoji("哭o(╥﹏╥)o了"), ["o(╥﹏╥)o"]);
+        assert_eq!(kaomoji("(;_;)"), ["(;_;)"]);
+        for raw in ["角度(30°)", "f(x^2)", "(x_1)", "(a;b)", "(1)"] {
+            assert!(kaomoji(raw).is_empty(), "{}", raw);
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use std::{collections::HashMap, fmt, ops::Range, sync::Arc};
+
+use serde::Serialize;
+
+use crate::{Dat, PostProcessor, Thulac};
+
+/// The kind of a named entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum EntityKind {
+    #[serde(rename = "PER")]
+    Person,
+    #[serde(rename = "ORG")]
+    Organization,
+    #[serde(rename = "LOC")]
+    Location,
+    #[serde(rename = "TIME")]
+    Time,
+}
+
+impl EntityKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EntityKind::Person => "PER",
+            EntityKind::Organization => "ORG",
+            EntityKind::Location => "LOC",
+            EntityKind::Time => "TIME",
+        }
+    }
+    /// The kind of the words the model tags `tag`.
+    fn of_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "np" => Some(EntityKind::Person),
+            "ni" => Some(EntityKind::Organization),
+            "ns" => Some(EntityKind::Location),
+            "t" => Some(EntityKind::Time),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EntityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A named entity found by [`EntityExtractor::extract`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Entity {
+    /// The byte range of the raw text.
+    pub range: Range<usize>,
+    /// The range of the words forming the entity.
+    pub words: Range<usize>,
+    pub kind: EntityKind,
+}
+
+/// Tags of the words allowed between an entity and a suffix extending it.
+const NOMINAL: [&str; 3] = ["n", "nz", "j"];
+
+/// Number of words allowed between an entity and a suffix extending it, as
+/// `计算机` in `清华大学计算机系`.
+const MAX_GAP: usize = 2;
+
+const ORGANIZATION_SUFFIXES: [&str; 30] = [
+    "大学",
+    "学院",
+    "中学",
+    "小学",
+    "公司",
+    "集团",
+    "银行",
+    "医院",
+    "研究所",
+    "研究院",
+    "实验室",
+    "委员会",
+    "协会",
+    "学会",
+    "政府",
+    "法院",
+    "检察院",
+    "出版社",
+    "电视台",
+    "中心",
+    "部",
+    "局",
+    "厅",
+    "处",
+    "系",
+    "所",
+    "院",
+    "社",
+    "队",
+    "团",
+];
+
+const LOCATION_SUFFIXES: [&str; 21] = [
+    "省", "市", "县", "区", "镇", "乡", "村", "州", "路", "街", "大街", "大道", "广场", "山", "河",
+    "江", "湖", "岛", "公园", "机场", "车站",
+];
+
+const PERSON_SUFFIXES: [&str; 19] = [
+    "老师", "先生", "女士", "小姐", "教授", "博士", "医生", "大夫", "院长", "校长", "主任", "经理",
+    "总理", "主席", "书记", "部长", "同学", "师傅", "律师",
+];
+
+/// Groups the words returned by [`crate::Thulac::cut`] into named entities
+/// of several words, such as `清华大学计算机系` or `张三老师`.
+///
+/// An entity starts at an entry of a dictionary or at a word tagged `np`,
+/// `ni`, `ns` or `t`. It then takes the following entities of the same kind,
+/// an organization after a place, and suffixes like `系`, `市` or `老师`,
+/// possibly after a few nouns. Suffixes of organizations and places extend
+/// either, changing the kind of the entity, while titles only extend
+/// persons. Suffixes of a single char must be whole words, so `城市` does
+/// not end a place.
+pub struct EntityExtractor {
+    dictionaries: Vec<Dictionary>,
+    suffixes: HashMap<String, EntityKind>,
+}
+
+/// A dictionary of entities, possibly spanning several words.
+enum Dictionary {
+    /// Entries loaded with `insert_end`, all of one kind.
+    Set(Dat, EntityKind),
+    /// Entries of a post-processor, of the kind of their tag, like the
+    /// places of `ns.bin` or the `ni` entries of a user dictionary.
+    Tagged(Arc<PostProcessor>),
+}
+
+impl Dictionary {
+    fn dat(&self) -> &Dat {
+        match self {
+            Dictionary::Set(dat, _) => dat,
+            Dictionary::Tagged(post) => post.dat(),
+        }
+    }
+    /// The kind of the entry whose leaf is `leaf`, if any.
+    fn kind(&self, leaf: i32) -> Option<EntityKind> {
+        match self {
+            Dictionary::Set(_, kind) => Some(*kind),
+            Dictionary::Tagged(post) => EntityKind::of_tag(post.entry(leaf).0),
+        }
+    }
+}
+
+impl Default for EntityExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EntityExtractor {
+    /// An extractor with common suffixes of organizations, places and
+    /// titles of persons, and no dictionary.
+    pub fn new() -> Self {
+        let mut extractor = Self {
+            dictionaries: Vec::new(),
+            suffixes: HashMap::new(),
+        };
+        for (suffixes, kind) in [
+            (&ORGANIZATION_SUFFIXES[..], EntityKind::Organization),
+            (&LOCATION_SUFFIXES[..], EntityKind::Location),
+            (&PERSON_SUFFIXES[..], EntityKind::Person),
+        ] {
+            for suffix in suffixes {
+                extractor.add_suffix(suffix, kind);
+            }
+        }
+        extractor
+    }
+    /// An extractor as [`EntityExtractor::new`], with the post-processors of
+    /// `thulac` as dictionaries: `ns.bin` and the entries of user
+    /// dictionaries tagged `np`, `ni`, `ns` or `t`.
+    pub fn from_thulac(thulac: &Thulac) -> Self {
+        let mut extractor = Self::new();
+        extractor.dictionaries.extend(
+            thulac
+                .posts
+                .iter()
+                .map(|post| Dictionary::Tagged(post.clone())),
+        );
+        extractor
+    }
+    /// Adds a dictionary whose entries, possibly spanning several words,
+    /// are entities of `kind`. The longest entry wins, then the first
+    /// dictionary added.
+    pub fn add_dictionary(&mut self, dat: Dat, kind: EntityKind) {
+        self.dictionaries.push(Dictionary::Set(dat, kind));
+    }
+    /// Adds or replaces a suffix. Suffixes of persons are titles, which
+    /// only extend persons.
+    pub fn add_suffix(&mut self, suffix: &str, kind: EntityKind) {
+        if kind != EntityKind::Time {
+            self.suffixes.insert(suffix.to_owned(), kind);
+        }
+    }
+    /// The kind of the longest suffix ending `word`.
+    fn suffix(&self, word: &str) -> Option<EntityKind> {
+        if let Some(&kind) = self.suffixes.get(word) {
+            return Some(kind);
+        }
+        word.char_indices()
+            .skip(1)
+            .map(|(i, _)| &word[i..])
+            .filter(|x| x.chars().nth(1).is_some())
+            .find_map(|x| self.suffixes.get(x).copied())
+    }
+    /// The entity starting at word `start`, as its end and kind.
+    fn start<S: AsRef<str>>(
+        &self,
+        words: &[(Range<usize>, S, &str)],
+        start: usize,
+    ) -> Option<(usize, EntityKind)> {
+        let mut best = None;
+        for dictionary in self.dictionaries.iter() {
+            let dat = dictionary.dat();
+            let mut node = dat.root();
+            for (i, (_, word, _)) in words.iter().enumerate().skip(start) {
+                let word = word.as_ref();
+                if word.is_empty() {
+                    break;
+                }
+                node = match dat.descendant(node, word) {
+                    Some(x) => x,
+                    None => break,
+                };
+                let Some(kind) = dat.child(node, '\0').and_then(|x| dictionary.kind(x)) else {
+                    continue;
+                };
+                if best.is_none_or(|(end, _)| i + 1 > end) {
+                    best = Some((i + 1, kind));
+                }
+            }
+        }
+        best.or_else(|| EntityKind::of_tag(words.get(start)?.2).map(|x| (start + 1, x)))
+    }
+    /// The entity ending at word `end` extended by one step, as its new end
+    /// and kind.
+    fn extend<S: AsRef<str>>(
+        &self,
+        words: &[(Range<usize>, S, &str)],
+        end: usize,
+        kind: EntityKind,
+    ) -> Option<(usize, EntityKind)> {
+        use EntityKind::*;
+        if let Some((next_end, next)) = self.start(words, end) {
+            return match (kind, next) {
+                (Person, Person) | (Location, Location) | (Time, Time) => Some((next_end, kind)),
+                (Location, Organization) => Some((next_end, next)),
+                _ => None,
+            };
+        }
+        for (i, (_, word, tag)) in words.iter().enumerate().skip(end).take(MAX_GAP + 1) {
+            if !NOMINAL.contains(tag) {
+                return None;
+            }
+            match (kind, self.suffix(word.as_ref())) {
+                (Person, Some(Person)) => return Some((i + 1, Person)),
+                (Organization | Location, Some(next @ (Organization | Location))) => {
+                    return Some((i + 1, next))
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+    /// The entities formed by `words`, in order and without overlaps.
+    pub fn extract<S: AsRef<str>>(&self, words: &[(Range<usize>, S, &str)]) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        let mut start = 0;
+        while start < words.len() {
+            let Some((mut end, mut kind)) = self.start(words, start) else {
+                start += 1;
+                continue;
+            };
+            while let Some(next) = self.extend(words, end, kind) {
+                (end, kind) = next;
+            }
+            entities.push(Entity {
+                range: words[start].0.start..words[end - 1].0.end,
+                words: start..end,
+                kind,
+            });
+            start = end;
+        }
+        entities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entities(extractor: &EntityExtractor, gold: &str) -> Vec<(String, EntityKind)> {
+        let (raw, words) = crate::tests::gold(gold);
+        extractor
+            .extract(&words)
+            .into_iter()
+            .map(|x| (raw[x.range].to_owned(), x.kind))
+            .collect()
+    }
+
+    fn entity(text: &str, kind: EntityKind) -> (String, EntityKind) {
+        (text.to_owned(), kind)
+    }
+
+    #[test]
+    fn test_extract() {
+        use EntityKind::*;
+        let mut extractor = EntityExtractor::new();
+        assert_eq!(
+            entities(
+                &extractor,
+                "清华_ns 大学_n 计算机_n 系_n 的_u 张三_np 老师_n"
+            ),
+            [
+                entity("清华大学计算机系", Organization),
+                entity("张三老师", Person)
+            ]
+        );
+        assert_eq!(
+            entities(&extractor, "北京_ns 市_n 城市_n 2021年10月18日_t 上午_t"),
+            [
+                entity("北京市", Location),
+                entity("2021年10月18日上午", Time)
+            ]
+        );
+        assert_eq!(
+            entities(&extractor, "北京_ns 市政府_ni 校长_n 张_np 三_np"),
+            [entity("北京市政府", Organization), entity("张三", Person)]
+        );
+        extractor.add_dictionary(Dat::build(vec![("紫荆公寓\0", 0)]), Location);
+        assert_eq!(
+            entities(&extractor, "紫荆_n 公寓_n 里_f"),
+            [entity("紫荆公寓", Location)]
+        );
+    }
+
+    #[test]
+    fn test_person_title() {
+        use EntityKind::*;
+        let extractor = EntityExtractor::new();
+        assert_eq!(
+            entities(&extractor, "张三_np 老师_n 说_v"),
+            [entity("张三老师", Person)]
+        );
+        assert_eq!(
+            entities(&extractor, "请_v 张_np 三_np 老师_n"),
+            [entity("张三老师", Person)]
+        );
+        assert_eq!(
+            entities(&extractor, "老师_n 张三_np"),
+            [entity("张三", Person)]
+        );
+        assert_eq!(
+            entities(&extractor, "北京_ns 老师_n"),
+            [entity("北京", Location)]
+        );
+    }
+
+    #[test]
+    fn test_organization_department() {
+        use EntityKind::*;
+        let extractor = EntityExtractor::new();
+        assert_eq!(
+            entities(&extractor, "清华大学_ni 计算机_n 系_n"),
+            [entity("清华大学计算机系", Organization)]
+        );
+        assert_eq!(
+            entities(&extractor, "清华_ns 大学_n 计算机_n 系_n"),
+            [entity("清华大学计算机系", Organization)]
+        );
+        assert_eq!(
+            entities(&extractor, "清华大学_ni 的_u 系_n"),
+            [entity("清华大学", Organization)]
+        );
+    }
+
+    #[test]
+    fn test_from_thulac() {
+        use EntityKind::*;
+        let mut thulac = crate::tests::dummy();
+        let places = Dat::load_set_txt(&mut "紫荆公寓\n".as_bytes(), true).unwrap();
+        thulac.add_postprocessor(PostProcessor::new(places, "ns".into()));
+        let user = "清华\tni\n树洞\tn\n";
+        thulac
+            .add_postprocessor(PostProcessor::load_txt(&mut user.as_bytes(), "uw".into()).unwrap());
+        let extractor = EntityExtractor::from_thulac(&thulac);
+        assert_eq!(
+            entities(&extractor, "紫荆_n 公寓_n 树洞_n 清华_n 计算机_n 系_n"),
+            [
+                entity("紫荆公寓", Location),
+                entity("清华计算机系", Organization)
+            ]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// A line of `label.txt` that cannot be parsed, numbered from 1.
+    InvalidLabel {
+        line: usize,
+        reason: &'static str,
+    },
+    /// A line of a gold corpus that cannot be parsed, numbered from 1.
+    InvalidCorpus {
+        line: usize,
+        reason: &'static str,
+    },
+    /// A line of a pattern file that cannot be parsed, numbered from 1.
+    InvalidPattern {
+        line: usize,
+        reason: String,
+    },
+    /// A line of a character class file that cannot be parsed, numbered
+    /// from 1.
+    InvalidCharClass {
+        line: usize,
+        reason: String,
+    },
+    /// A line of a rules file that cannot be parsed or whose test fails,
+    /// numbered from 1.
+    InvalidRule {
+        line: usize,
+        reason: String,
+    },
+    /// The model found no label sequence satisfying the POC constraints.
+    NoPath,
+    /// The model input and the raw text went out of step while emitting
+    /// words, or the scores given to [`crate::Model::decode`] do not match
+    /// its input.
+    Misaligned,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::InvalidLabel { line, reason } => {
+                write!(f, "invalid label at line {}: {}", line, reason)
+            }
+            Error::InvalidCorpus { line, reason } => {
+                write!(f, "invalid corpus at line {}: {}", line, reason)
+            }
+            Error::InvalidPattern { line, reason } => {
+                write!(f, "invalid pattern at line {}: {}", line, reason)
+            }
+            Error::InvalidCharClass { line, reason } => {
+                write!(f, "invalid character class at line {}: {}", line, reason)
+            }
+            Error::InvalidRule { line, reason } => {
+                write!(f, "invalid rule at line {}: {}", line, reason)
+            }
+            Error::NoPath => write!(f, "no valid segmentation path"),
+            Error::Misaligned => write!(f, "input misaligned with raw text"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
@@ -0,0 +1,210 @@
+use std::{collections::BTreeMap, io::BufRead};
+
+use serde::Serialize;
+
+use crate::{Dat, Error, Result, Thulac, Word};
+
+/// Counts accumulated by comparing the segmentation of a gold corpus with
+/// the output of [`Thulac::cut`]. Words are matched by their byte range in
+/// the raw text, so a word is correct only if both of its boundaries are.
+#[derive(Default, Serialize)]
+pub struct Evaluation {
+    pub sentences: usize,
+    pub gold_words: usize,
+    pub predicted_words: usize,
+    /// Predicted words with the same range as a gold word.
+    pub correct_words: usize,
+    /// Correct words that also have the gold tag.
+    pub correct_tags: usize,
+    /// Gold words missing from the training dictionary.
+    pub oov_words: usize,
+    pub correct_oov_words: usize,
+    /// Gold tag to predicted tag to count, over correct words.
+    pub confusion: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+/// Metrics derived from an [`Evaluation`], as written in JSON reports.
+#[derive(Serialize)]
+pub struct Report<'a> {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    pub pos_accuracy: f64,
+    pub pos_f1: f64,
+    pub oov_recall: Option<f64>,
+    pub counts: &'a Evaluation,
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+fn f1(p: f64, r: f64) -> f64 {
+    if p + r == 0.0 {
+        0.0
+    } else {
+        2.0 * p * r / (p + r)
+    }
+}
+
+/// Parses a line of `word_tag` pairs separated by whitespace, returning the
+/// raw text and the byte range of each word in it.
+pub fn parse_gold<'a>(line: &'a str, separator: &str) -> Option<(String, Vec<Word<'a, 'a>>)> {
+    let mut raw = String::new();
+    let mut words = Vec::new();
+    for pair in line.split_whitespace() {
+        let (word, tag) = pair.rsplit_once(separator)?;
+        if word.is_empty() || tag.is_empty() {
+            return None;
+        }
+        let start = raw.len();
+        raw.push_str(word);
+        words.push((start..raw.len(), word, tag));
+    }
+    Some((raw, words))
+}
+
+impl Evaluation {
+    /// Adds one sentence. Words without text, i.e. whitespace, are ignored.
+    pub fn add(&mut self, gold: &[Word], predicted: &[Word], lexicon: Option<&Dat>) {
+        let predicted = predicted
+            .iter()
+            .filter(|(_, word, _)| !word.is_empty())
+            .collect::<Vec<_>>();
+        self.sentences += 1;
+        self.gold_words += gold.len();
+        self.predicted_words += predicted.len();
+        let mut predicted = predicted.into_iter().peekable();
+        for (range, word, tag) in gold {
+            while predicted
+                .peek()
+                .is_some_and(|(x, _, _)| x.start < range.start)
+            {
+                predicted.next();
+            }
+            let hit = predicted.peek().filter(|(x, _, _)| x == range).copied();
+            let oov = lexicon.is_some_and(|x| !x.contains(word));
+            if oov {
+                self.oov_words += 1;
+            }
+            if let Some((_, _, predicted_tag)) = hit {
+                self.correct_words += 1;
+                if oov {
+                    self.correct_oov_words += 1;
+                }
+                if tag == predicted_tag {
+                    self.correct_tags += 1;
+                }
+                *self
+                    .confusion
+                    .entry(tag.to_string())
+                    .or_default()
+                    .entry(predicted_tag.to_string())
+                    .or_default() += 1;
+            }
+        }
+    }
+    pub fn precision(&self) -> f64 {
+        ratio(self.correct_words, self.predicted_words)
+    }
+    pub fn recall(&self) -> f64 {
+        ratio(self.correct_words, self.gold_words)
+    }
+    pub fn f1(&self) -> f64 {
+        f1(self.precision(), self.recall())
+    }
+    /// Fraction of correctly segmented words that are correctly tagged.
+    pub fn pos_accuracy(&self) -> f64 {
+        ratio(self.correct_tags, self.correct_words)
+    }
+    /// F1 counting a word as correct only if its tag is correct as well.
+    pub fn pos_f1(&self) -> f64 {
+        f1(
+            ratio(self.correct_tags, self.predicted_words),
+            ratio(self.correct_tags, self.gold_words),
+        )
+    }
+    pub fn oov_recall(&self) -> Option<f64> {
+        (self.oov_words != 0).then(|| ratio(self.correct_oov_words, self.oov_words))
+    }
+    pub fn report(&self) -> Report<'_> {
+        Report {
+            precision: self.precision(),
+            recall: self.recall(),
+            f1: self.f1(),
+            pos_accuracy: self.pos_accuracy(),
+            pos_f1: self.pos_f1(),
+            oov_recall: self.oov_recall(),
+            counts: self,
+        }
+    }
+}
+
+/// Segments the raw text of every line of a gold corpus in `word_tag` format
+/// and compares the result with it. Empty lines are skipped. `lexicon` is the
+/// dictionary of the training data, a set loaded with `insert_end`, used to
+/// find out-of-vocabulary words.
+pub fn evaluate<R: BufRead>(
+    thulac: &Thulac,
+    reader: R,
+    separator: &str,
+    lexicon: Option<&Dat>,
+) -> Result<Evaluation> {
+    let mut evaluation = Evaluation::default();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (raw, gold) = parse_gold(&line, separator).ok_or(Error::InvalidCorpus {
+            line: i + 1,
+            reason: "expected word and tag separated by the separator",
+        })?;
+        let preprocess = thulac.preprocess(&raw);
+        let predicted = thulac.try_cut(&preprocess)?;
+        evaluation.add(&gold, &predicted, lexicon);
+    }
+    Ok(evaluation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluation() {
+        let (raw, gold) = parse_gold("我_r 爱_v 北京_ns 天安门_ns", "_").unwrap();
+        assert_eq!(raw, "我爱北京天安门");
+        let predicted = [
+            (0..3, "我", "r"),
+            (3..6, "爱", "v"),
+            (6..21, "北京天安门", "ns"),
+        ];
+        let lexicon = Dat::build(vec![("我\0", 0), ("爱\0", 0), ("北京\0", 0)]);
+        let mut evaluation = Evaluation::default();
+        evaluation.add(&gold, &predicted, Some(&lexicon));
+        assert_eq!(evaluation.correct_words, 2);
+        assert_eq!(evaluation.precision(), 2.0 / 3.0);
+        assert_eq!(evaluation.recall(), 0.5);
+        assert_eq!(evaluation.pos_accuracy(), 1.0);
+        assert_eq!(evaluation.oov_recall(), Some(0.0));
+        assert_eq!(evaluation.confusion["v"]["v"], 1);
+        assert!(parse_gold("我 爱_v", "_").is_none());
+    }
+
+    #[test]
+    fn test_evaluate_dict() {
+        // dictionaries of training data often repeat words
+        let lexicon = Dat::load_set_txt(&mut "我\n爱\n我\n\n".as_bytes(), true).unwrap();
+        let thulac = crate::tests::dummy();
+        let gold = "我_r 爱_v 北京_ns\n".as_bytes();
+        let evaluation = evaluate(&thulac, gold, "_", Some(&lexicon)).unwrap();
+        assert_eq!(evaluation.sentences, 1);
+        assert_eq!(evaluation.gold_words, 3);
+        assert_eq!(evaluation.oov_words, 1);
+    }
+}
@@ -0,0 +1,109 @@
+use std::{collections::HashSet, ops::Range};
+
+use crate::Dat;
+
+/// One stopword per line.
+const STOPWORDS: &str = include_str!("../model/stopwords.txt");
+
+/// Drops words that are useless for indexing, like the `-filter` option of
+/// THULAC: stopwords, words with a denied tag and, if some tags are allowed,
+/// words with any other tag. Whitespace between words is always dropped, so
+/// filtered words no longer cover the raw text.
+pub struct Filter {
+    /// Stopwords followed by `\0`.
+    stopwords: Option<Dat>,
+    deny: HashSet<String>,
+    allow: Option<HashSet<String>>,
+}
+
+impl Default for Filter {
+    /// Drops punctuation `w`, auxiliary words `u` and the bundled Chinese
+    /// stopwords.
+    fn default() -> Self {
+        Self::new()
+            .with_stopwords(Self::default_stopwords())
+            .deny_tags(["w", "u"])
+    }
+}
+
+impl Filter {
+    /// The bundled Chinese stopwords: function words, pronouns and
+    /// particles.
+    pub fn default_stopwords() -> Dat {
+        let stopwords = STOPWORDS
+            .lines()
+            .map(|x| x.to_owned() + "\0")
+            .collect::<Vec<_>>();
+        Dat::build(stopwords.iter().map(|x| (&x[..], 0)).collect())
+    }
+    /// A filter dropping whitespace only.
+    pub fn new() -> Self {
+        Self {
+            stopwords: None,
+            deny: HashSet::new(),
+            allow: None,
+        }
+    }
+    /// Replaces the stopwords by a set loaded with `insert_end`, as by
+    /// [`Dat::load_set_txt`].
+    pub fn with_stopwords(mut self, stopwords: Dat) -> Self {
+        self.stopwords = Some(stopwords);
+        self
+    }
+    /// Drops the words tagged with one of `tags`.
+    pub fn deny_tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tags: I) -> Self {
+        self.deny.extend(tags.into_iter().map(Into::into));
+        self
+    }
+    /// Keeps only the words tagged with one of `tags`, and of the tags
+    /// allowed before.
+    pub fn allow_tags<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tags: I) -> Self {
+        self.allow
+            .get_or_insert_with(HashSet::new)
+            .extend(tags.into_iter().map(Into::into));
+        self
+    }
+    pub fn keep(&self, word: &str, tag: &str) -> bool {
+        !word.is_empty()
+            && !self.deny.contains(tag)
+            && self.allow.as_ref().is_none_or(|x| x.contains(tag))
+            && self.stopwords.as_ref().is_none_or(|x| !x.contains(word))
+    }
+    pub fn apply<'a, 'b>(
+        &self,
+        mut words: Vec<(Range<usize>, &'a str, &'b str)>,
+    ) -> Vec<(Range<usize>, &'a str, &'b str)> {
+        words.retain(|(_, word, tag)| self.keep(word, tag));
+        words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let words = vec![
+            (0..3, "我", "r"),
+            (3..6, "的", "u"),
+            (6..12, "书包", "n"),
+            (12..13, "", "w"),
+            (13..16, "。", "w"),
+            (16..19, "跑", "v"),
+        ];
+        let words = |filter: &Filter| {
+            filter
+                .apply(words.clone())
+                .into_iter()
+                .map(|x| x.1)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(words(&Filter::default()), ["书包", "跑"]);
+        assert_eq!(words(&Filter::new()), ["我", "的", "书包", "。", "跑"]);
+        let stopwords = Dat::load_set_txt(&mut "书包\n\n书包\n".as_bytes(), true).unwrap();
+        let filter = Filter::default().with_stopwords(stopwords);
+        assert_eq!(words(&filter), ["我", "跑"]);
+        assert_eq!(words(&filter.allow_tags(["n", "v"])), ["跑"]);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::PatternRecognizer;
+
+/// References to other posts, like `#123456`.
+const POST_ID: &str = r"#\d{4,8}(?-u:\b)";
+/// The name a reply starts with, like `[Alice]` or `[洞主]`.
+const REPLY_NAME: &str = r"(?m)^\[([^\[\]\s]{1,24})\]";
+/// The name a reply answers to, like `Re Bob:`.
+const RE_NAME: &str = r"(?:^|\s)Re ([^\s:：]{1,24})[:：]";
+/// Pseudonyms given to repliers in the order they join a thread.
+const NAMES: &str = "洞主|(?-u:\\b)(?:Alice|Bob|Carol|Dave|Eve|Francis|Grace|Hans|Isabella|\
+                     Jason|Kate|Louis|Margaret|Nathan|Olivia|Paul|Queen|Richard|Susan|Thomas|\
+                     Uma|Vivian|Winnie|Xander|Yasmine|Zach)(?-u:\\b)";
+
+impl PatternRecognizer {
+    /// Post references tagged `pid`, and reply names tagged `name`.
+    pub fn hole() -> Self {
+        let mut recognizer = Self::new();
+        for (pattern, tag) in [
+            (POST_ID, "pid"),
+            (REPLY_NAME, "name"),
+            (RE_NAME, "name"),
+            (NAMES, "name"),
+        ] {
+            recognizer.add(pattern, tag.into()).unwrap();
+        }
+        recognizer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{recognize::resolve, Recognizer};
+
+    #[test]
+    fn test_hole() {
+        let hole = PatternRecognizer::hole();
+        let raw = "[Alice] Re 洞主: 同意#123456的说法，Bob说得对\n[Carol] 顶";
+        let mut spans = Vec::new();
+        hole.recognize(raw, &mut spans);
+        let spans = resolve(raw, spans)
+            .into_iter()
+            .map(|(range, tag)| (&raw[range], tag))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                ("Alice", "name"),
+                ("洞主", "name"),
+                ("#123456", "pid"),
+                ("Bob", "name"),
+                ("Carol", "name"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{Read, Result, Seek, Write},
+    ops::Range,
+};
+
+use crate::{Dat, Filter};
+
+/// Tags of the words that can be keywords: nouns, names and verbs.
+const TAGS: [&str; 7] = ["n", "np", "ns", "ni", "nz", "j", "v"];
+
+/// Iterations of TextRank, which usually converges well before.
+const ITERATIONS: usize = 50;
+
+/// Damping factor of TextRank.
+const DAMPING: f64 = 0.85;
+
+/// Counts the documents of a corpus each word occurs in, to build an
+/// [`Idf`] table.
+#[derive(Default)]
+pub struct IdfBuilder {
+    documents: usize,
+    counts: HashMap<String, usize>,
+}
+
+impl IdfBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Counts a document, given as its words.
+    pub fn add_document<'a, I: IntoIterator<Item = &'a str>>(&mut self, words: I) {
+        self.documents += 1;
+        let words = words
+            .into_iter()
+            .filter(|x| !x.is_empty())
+            .collect::<HashSet<_>>();
+        for word in words {
+            *self.counts.entry(word.to_owned()).or_default() += 1;
+        }
+    }
+    /// Builds the table, leaving out words found in fewer than `min_count`
+    /// documents, which are then as rare as unknown words.
+    pub fn build(&self, min_count: usize) -> Idf {
+        let keys = self
+            .counts
+            .iter()
+            .filter(|(_, &count)| count >= min_count)
+            .map(|(word, &count)| (word.to_owned() + "\0", count))
+            .chain([("\0".to_owned(), self.documents)])
+            .collect::<Vec<_>>();
+        let dat = Dat::build(
+            keys.iter()
+                .map(|(x, count)| (&x[..], (*count).min(i32::MAX as usize) as i32))
+                .collect(),
+        );
+        Idf { dat }
+    }
+}
+
+/// Inverse document frequencies of words, stored as a [`Dat`] mapping each
+/// word followed by `\0` to the number of documents it occurs in, and the
+/// empty word, which occurs in all of them, to the number of documents.
+pub struct Idf {
+    dat: Dat,
+}
+
+impl Idf {
+    pub fn load<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        Ok(Self {
+            dat: Dat::load(reader)?,
+        })
+    }
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.dat.save(writer)
+    }
+    /// The number of documents `word` occurs in.
+    pub fn frequency(&self, word: &str) -> usize {
+        self.dat
+            .descendant(self.dat.root(), word)
+            .and_then(|x| self.dat.child(x, '\0'))
+            .map_or(0, |x| self.dat.base(x) as usize)
+    }
+    pub fn documents(&self) -> usize {
+        self.frequency("")
+    }
+    /// The smoothed inverse document frequency of `word`, which is highest
+    /// for unknown words and 1 for words occurring in every document.
+    pub fn idf(&self, word: &str) -> f64 {
+        let documents = self.documents() as f64;
+        ((documents + 1.0) / (self.frequency(word) as f64 + 1.0)).ln() + 1.0
+    }
+}
+
+/// Finds the keywords of a text from the words returned by
+/// [`crate::Thulac::cut`], either by TF-IDF or by TextRank. Candidates are
+/// the words of several chars kept by a filter, which by default keeps
+/// nouns, names and verbs that are not stopwords.
+pub struct KeywordExtractor {
+    filter: Filter,
+    idf: Option<Idf>,
+    window: usize,
+}
+
+impl Default for KeywordExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sorts keywords by decreasing score, then by word, and keeps `top`.
+fn top_keywords(mut keywords: Vec<(&str, f64)>, top: usize) -> Vec<(&str, f64)> {
+    keywords.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.0.cmp(b.0))
+    });
+    keywords.truncate(top);
+    keywords
+}
+
+impl KeywordExtractor {
+    /// An extractor without IDF table, so TF-IDF ranks by term frequency,
+    /// and with a TextRank window of 5 words.
+    pub fn new() -> Self {
+        Self {
+            filter: Filter::default().allow_tags(TAGS),
+            idf: None,
+            window: 5,
+        }
+    }
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+    pub fn with_idf(mut self, idf: Idf) -> Self {
+        self.idf = Some(idf);
+        self
+    }
+    /// Sets the number of consecutive candidates that co-occur in TextRank.
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(2);
+        self
+    }
+    /// The candidates among `words`, in order.
+    fn candidates<'w, S: AsRef<str>>(&self, words: &'w [(Range<usize>, S, &str)]) -> Vec<&'w str> {
+        words
+            .iter()
+            .map(|(_, word, tag)| (word.as_ref(), *tag))
+            .filter(|(word, tag)| word.chars().nth(1).is_some() && self.filter.keep(word, tag))
+            .map(|x| x.0)
+            .collect()
+    }
+    /// The `top` keywords of `words` by TF-IDF, with their scores.
+    pub fn tfidf<'w, S: AsRef<str>>(
+        &self,
+        words: &'w [(Range<usize>, S, &str)],
+        top: usize,
+    ) -> Vec<(&'w str, f64)> {
+        let candidates = self.candidates(words);
+        let mut counts = HashMap::<_, usize>::new();
+        for word in candidates.iter() {
+            *counts.entry(*word).or_default() += 1;
+        }
+        let total = candidates.len() as f64;
+        let keywords = counts
+            .into_iter()
+            .map(|(word, count)| {
+                let idf = self.idf.as_ref().map_or(1.0, |x| x.idf(word));
+                (word, count as f64 / total * idf)
+            })
+            .collect();
+        top_keywords(keywords, top)
+    }
+    /// The `top` keywords of `words` by TextRank over the graph of
+    /// candidates co-occurring within the window, with their scores scaled
+    /// so the best one is 1.
+    pub fn textrank<'w, S: AsRef<str>>(
+        &self,
+        words: &'w [(Range<usize>, S, &str)],
+        top: usize,
+    ) -> Vec<(&'w str, f64)> {
+        let candidates = self.candidates(words);
+        let mut indices = HashMap::new();
+        let mut nodes = Vec::new();
+        let candidates = candidates
+            .into_iter()
+            .map(|word| {
+                *indices.entry(word).or_insert_with(|| {
+                    nodes.push(word);
+                    nodes.len() - 1
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut edges = vec![BTreeMap::<usize, f64>::new(); nodes.len()];
+        for (i, &a) in candidates.iter().enumerate() {
+            for &b in candidates.iter().skip(i + 1).take(self.window - 1) {
+                if a != b {
+                    *edges[a].entry(b).or_default() += 1.0;
+                    *edges[b].entry(a).or_default() += 1.0;
+                }
+            }
+        }
+        let degrees = edges
+            .iter()
+            .map(|x| x.values().sum::<f64>())
+            .collect::<Vec<_>>();
+        let mut scores = vec![1.0; nodes.len()];
+        for _ in 0..ITERATIONS {
+            scores = edges
+                .iter()
+                .map(|x| {
+                    let sum = x
+                        .iter()
+                        .map(|(&j, weight)| weight / degrees[j] * scores[j])
+                        .sum::<f64>();
+                    1.0 - DAMPING + DAMPING * sum
+                })
+                .collect();
+        }
+        let max = scores.iter().copied().fold(f64::MIN_POSITIVE, f64::max);
+        let keywords = nodes
+            .into_iter()
+            .zip(scores)
+            .map(|(word, score)| (word, score / max))
+            .collect();
+        top_keywords(keywords, top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keywords() {
+        let mut builder = IdfBuilder::new();
+        for document in ["清华 大学 学生", "北京 大学 学生", "大学 老师"] {
+            builder.add_document(document.split(' '));
+        }
+        let mut bin = Vec::new();
+        builder.build(1).save(&mut bin).unwrap();
+        let idf = Idf::load(&mut std::io::Cursor::new(bin)).unwrap();
+        assert_eq!(idf.documents(), 3);
+        assert_eq!(idf.frequency("学生"), 2);
+        assert_eq!(idf.idf("大学"), 1.0);
+        assert!(idf.idf("清华") < idf.idf("未知"));
+
+        let words = "清华_ns 大学_n 的_u 学生_n 和_c 清华_ns 大学_n 的_u 老师_n 讨论_v 人_n";
+        let (_, words) = crate::tests::gold(words);
+        let extractor = KeywordExtractor::new().with_idf(idf);
+        let keywords = extractor.tfidf(&words, 2);
+        assert_eq!(
+            keywords.iter().map(|x| x.0).collect::<Vec<_>>(),
+            ["清华", "讨论"]
+        );
+        let keywords = extractor.textrank(&words, 10);
+        assert_eq!(keywords.len(), 5);
+        assert_eq!(keywords[0].1, 1.0);
+        assert!(keywords.iter().all(|x| x.0 != "人" && x.0 != "的"));
+    }
+}
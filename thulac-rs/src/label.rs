@@ -1,9 +1,6 @@
-use std::{
-    io::{BufRead, Result},
-    usize,
-};
+use std::io::BufRead;
 
-use crate::Poc;
+use crate::{Error, Poc, Result};
 
 pub struct Label {
     labels: Vec<(Poc, String)>,
@@ -13,24 +10,38 @@ pub struct Label {
 
 impl Label {
     pub fn load<R: BufRead>(reader: &mut R) -> Result<Self> {
-        let mut labels = reader.lines().collect::<Result<Vec<String>>>()?;
+        let mut labels = reader.lines().collect::<std::io::Result<Vec<String>>>()?;
         while let Some(true) = labels.last().map(|x| x.is_empty()) {
             labels.pop();
         }
         let labels = labels
             .into_iter()
-            .map(|x| {
+            .enumerate()
+            .map(|(i, x)| {
+                let invalid = |reason| Error::InvalidLabel {
+                    line: i + 1,
+                    reason,
+                };
                 let mut chars = x.chars();
-                let poc = match chars.next().expect("empty line in labels") {
+                let poc = match chars.next().ok_or_else(|| invalid("empty line"))? {
                     '0' => Poc::B,
                     '1' => Poc::M,
                     '2' => Poc::E,
                     '3' => Poc::S,
-                    _ => panic!("unknown poc in labels"),
+                    _ => return Err(invalid("unknown poc")),
                 };
-                (poc, chars.as_str().to_string())
+                if chars.as_str().is_empty() {
+                    return Err(invalid("missing tag"));
+                }
+                Ok((poc, chars.as_str().to_string()))
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>>>()?;
+        if labels.is_empty() {
+            return Err(Error::InvalidLabel {
+                line: 1,
+                reason: "no labels",
+            });
+        }
         let mut poc2label: [Vec<usize>; 16] = Default::default();
         labels.iter().enumerate().for_each(|(i, &(poc, _))| {
             for j in 0..16 {
@@ -80,3 +91,19 @@ impl Label {
         &self.prev_labels[index]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_labels() {
+        for (text, line) in [("0n\n\n2n\n", 2), ("0n\n4n\n", 2), ("3\n", 1), ("", 1)] {
+            match Label::load(&mut text.as_bytes()) {
+                Err(Error::InvalidLabel { line: actual, .. }) => assert_eq!(actual, line),
+                _ => panic!("{:?} should be rejected", text),
+            }
+        }
+        assert!(Label::load(&mut "0n\n2n\n3w\n\n".as_bytes()).is_ok());
+    }
+}
@@ -0,0 +1,177 @@
+use std::ops::Range;
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+/// A term to index, with its byte range in the raw text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Term {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// Splits Latin words, which the model keeps whole as runs of ASCII
+/// letters, digits and symbols like `iPhone13-Pro/Max`, into sub-tokens for
+/// the index. The whole word is kept as a compound term for exact matching.
+pub struct LatinTokenizer {
+    stemmer: Option<Stemmer>,
+}
+
+impl Default for LatinTokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatinTokenizer {
+    pub fn new() -> Self {
+        Self { stemmer: None }
+    }
+    /// Also stems sub-tokens with the English Snowball stemmer.
+    pub fn with_stemming() -> Self {
+        Self {
+            stemmer: Some(Stemmer::create(Algorithm::English)),
+        }
+    }
+    /// Whether `word` has a letter or digit and only ASCII or full-width
+    /// ASCII chars.
+    pub fn is_latin(word: &str) -> bool {
+        word.chars().any(char::is_alphanumeric)
+            && word
+                .chars()
+                .all(|ch| ch.is_ascii_graphic() || ('！'..='～').contains(&ch))
+    }
+    /// The byte ranges of the sub-tokens of `word`, split at symbols, between
+    /// letters and digits, and at case changes, so `HTTPServer2` gives
+    /// `HTTP`, `Server` and `2`.
+    pub fn split(word: &str) -> Vec<Range<usize>> {
+        let mut parts = Vec::new();
+        let mut start = None;
+        let mut prev = None::<char>;
+        let mut chars = word.char_indices().peekable();
+        while let Some((i, ch)) = chars.next() {
+            if !ch.is_alphanumeric() {
+                if let Some(start) = start.take() {
+                    parts.push(start..i);
+                }
+                prev = None;
+                continue;
+            }
+            let next = chars.peek().map(|x| x.1);
+            let boundary = prev.is_some_and(|prev| {
+                prev.is_numeric() != ch.is_numeric()
+                    || prev.is_lowercase() && ch.is_uppercase()
+                    || prev.is_uppercase()
+                        && ch.is_uppercase()
+                        && next.is_some_and(char::is_lowercase)
+            });
+            match start {
+                Some(x) if boundary => {
+                    parts.push(x..i);
+                    start = Some(i);
+                }
+                Some(_) => (),
+                None => start = Some(i),
+            }
+            prev = Some(ch);
+        }
+        if let Some(start) = start {
+            parts.push(start..word.len());
+        }
+        parts
+    }
+    /// The terms of `word`, found at `range` of the raw text: the lowercased
+    /// word, then for Latin words its lowercased and possibly stemmed
+    /// sub-tokens, unless the only one is the word itself. Sub-tokens get
+    /// the range of the whole word if `word` was normalized to a different
+    /// length.
+    pub fn terms(&self, range: Range<usize>, word: &str) -> Vec<Term> {
+        let text = word.to_lowercase();
+        let mut terms = Vec::new();
+        if Self::is_latin(word) {
+            for part in Self::split(word) {
+                let mut sub = word[part.clone()].to_lowercase();
+                if let Some(stemmer) = self.stemmer.as_ref() {
+                    sub = stemmer.stem(&sub).into_owned();
+                }
+                let range = if word.len() == range.len() {
+                    range.start + part.start..range.start + part.end
+                } else {
+                    range.clone()
+                };
+                terms.push(Term { range, text: sub });
+            }
+        }
+        if terms.len() == 1 && terms[0].text == text {
+            terms.clear();
+        }
+        terms.insert(0, Term { range, text });
+        terms
+    }
+    /// The terms of each word returned by [`crate::Thulac::cut`], skipping
+    /// whitespace.
+    pub fn index<S: AsRef<str>>(&self, words: &[(Range<usize>, S, &str)]) -> Vec<Term> {
+        words
+            .iter()
+            .filter(|(_, word, _)| !word.as_ref().is_empty())
+            .flat_map(|(range, word, _)| self.terms(range.clone(), word.as_ref()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let split = |word| {
+            LatinTokenizer::split(word)
+                .into_iter()
+                .map(|x| &word[x])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            split("iPhone13-Pro/Max"),
+            ["i", "Phone", "13", "Pro", "Max"]
+        );
+        assert_eq!(split("HTTPServer2"), ["HTTP", "Server", "2"]);
+        assert_eq!(split("snake_case__"), ["snake", "case"]);
+        assert_eq!(split("ＧＰＴ４"), ["ＧＰＴ", "４"]);
+        assert_eq!(split("--"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_terms() {
+        let term = |range, text: &str| Term {
+            range,
+            text: text.into(),
+        };
+        let latin = LatinTokenizer::new();
+        assert_eq!(
+            latin.terms(3..11, "COVID-19"),
+            [
+                term(3..11, "covid-19"),
+                term(3..8, "covid"),
+                term(9..11, "19")
+            ]
+        );
+        assert_eq!(latin.terms(0..5, "hello"), [term(0..5, "hello")]);
+        assert_eq!(latin.terms(0..6, "你好"), [term(0..6, "你好")]);
+        assert_eq!(
+            latin.terms(0..6, "A1"),
+            [term(0..6, "a1"), term(0..6, "a"), term(0..6, "1")],
+            "sub-tokens of normalized words get the range of the word"
+        );
+        let stemming = LatinTokenizer::with_stemming();
+        assert_eq!(
+            stemming.terms(0..14, "RunningQueries"),
+            [
+                term(0..14, "runningqueries"),
+                term(0..7, "run"),
+                term(7..14, "queri")
+            ]
+        );
+        let words = [(0..6, "你好", "v"), (6..7, "", "w"), (7..9, "Hi", "x")];
+        assert_eq!(latin.index(&words).len(), 2);
+    }
+}
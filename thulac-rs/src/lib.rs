@@ -1,39 +1,128 @@
-#![feature(char_indices_offset)]
 #![feature(seek_stream_len)]
-#![feature(result_flattening)]
 
 mod dat;
+mod discover;
+mod emoji;
+mod entity;
+mod error;
+mod evaluate;
+mod filter;
+mod hole;
+mod keywords;
 mod label;
+mod latin;
 mod model;
+mod normalization;
+mod numeral;
+mod oov;
+mod output;
+mod pinyin;
 mod poc;
 mod post;
+mod recognize;
+mod rules;
+mod shared;
+mod split;
 mod t2s;
 
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::{BufReader, ErrorKind, Result};
+use std::io::{BufReader, ErrorKind};
 use std::ops::Range;
 use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
 pub use dat::Dat;
+pub use discover::{Candidate, Discovery};
+pub use emoji::EmojiRecognizer;
+pub use entity::{Entity, EntityExtractor, EntityKind};
+pub use error::{Error, Result};
+pub use evaluate::{evaluate, parse_gold, Evaluation, Report};
+pub use filter::Filter;
+pub use keywords::{Idf, IdfBuilder, KeywordExtractor};
 pub use label::Label;
-pub use model::Model;
+pub use latin::{LatinTokenizer, Term};
+pub use model::{Model, Scratch};
+pub use normalization::Normalization;
+pub use numeral::{normalize_numeral, NumeralRecognizer, NumeralValue};
+pub use oov::{Lexicon, OovAnalyzer};
+pub use output::{Format, Formatter};
+pub use pinyin::{Annotation, Pinyin};
 pub use poc::punc_adjust;
-pub use poc::Poc;
-pub use post::PostProcessor;
-pub use t2s::T2S;
+pub use poc::{CharClassifier, CharKind, Poc};
+pub use post::{PostProcessor, PostProcessorId};
+pub use recognize::{PatternRecognizer, Recognizer};
+pub use rules::RuleSet;
+pub use shared::SharedThulac;
+pub use split::Splitter;
+pub use t2s::{S2T, T2S};
+
+/// A word produced by [`Thulac::cut`]: its byte range in the raw text, the
+/// word itself as seen by the model, and its tag. Whitespace between words
+/// is returned as words without text.
+pub type Word<'a, 'b> = (Range<usize>, &'a str, &'b str);
 
+/// A loaded segmenter. It is never mutated by segmentation, so one instance
+/// can be shared behind an `Arc` by any number of threads.
+///
+/// Cloning shares the model and dictionaries instead of copying them. To
+/// change dictionaries without downtime, wrap the instance in a
+/// [`SharedThulac`], which swaps in a modified clone while calls in flight
+/// finish on the old instance.
+#[derive(Clone)]
 pub struct Thulac {
-    label: Label,
-    model: Model,
-    dat: Dat,
-    t2s: Option<T2S>,
-    posts: Vec<PostProcessor>,
+    label: Arc<Label>,
+    model: Arc<Model>,
+    dat: Arc<Dat>,
+    chars: Arc<CharClassifier>,
+    normalization: Normalization,
+    t2s: Option<Arc<T2S>>,
+    /// Only used on output, see [`Thulac::s2t`].
+    s2t: Option<Arc<S2T>>,
+    splitters: Vec<Arc<Splitter>>,
+    posts: Vec<Arc<PostProcessor>>,
+    /// The id of each of `posts`.
+    post_ids: Vec<PostProcessorId>,
+    next_post_id: u64,
+    rules: Vec<Arc<RuleSet>>,
+    filter: Option<Arc<Filter>>,
+    recognizers: Vec<Arc<dyn Recognizer>>,
+}
+
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Thulac>();
+};
+
+/// Loads the conversion table `{name}.bin` of the model directory, with the
+/// phrases of `{name}_phrases.txt` if any, or `None` without the table.
+fn load_conversion(path: &Path, name: &str) -> Result<Option<T2S>> {
+    let mut conversion = match File::open(path.join(format!("{}.bin", name))) {
+        Ok(mut file) => T2S::load(&mut file)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    match File::open(path.join(format!("{}_phrases.txt", name))) {
+        Ok(file) => conversion.load_phrases_txt(&mut BufReader::new(file))?,
+        Err(e) if e.kind() == ErrorKind::NotFound => (),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(Some(conversion))
 }
 
-pub struct Preprocess<'a> {
+/// The raw text prepared for the model by [`Thulac::preprocess`]. `'t` is
+/// the lifetime of the tags of recognized tokens, borrowed from [`Thulac`].
+pub struct Preprocess<'a, 't> {
     raw: &'a str,
     input: String,
+    /// The byte range of `raw` each char of `input` comes from.
+    offsets: Vec<Range<usize>>,
     pocs: Vec<Poc>,
+    /// Tokens found by recognizers, as ranges of chars of `input`.
+    spans: Vec<(Range<usize>, &'t str)>,
 }
 
 impl Thulac {
@@ -42,93 +131,576 @@ impl Thulac {
         let label = Label::load(&mut BufReader::new(File::open(path.join("label.txt"))?))?;
         let model = Model::load(&mut File::open(path.join("model.bin"))?)?;
         let dat = Dat::load(&mut File::open(path.join("dat.bin"))?)?;
-        let t2s = File::open(path.join("t2s.bin"))
-            .map(|mut x| T2S::load(&mut x))
-            .flatten()
-            .map(Option::Some)
-            .or_else(|e| {
-                if e.kind() == ErrorKind::NotFound {
-                    Ok(None)
-                } else {
-                    Err(e)
-                }
-            })?;
+        let chars = match File::open(path.join("chars.txt")) {
+            Ok(file) => CharClassifier::load_txt(&mut BufReader::new(file))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => CharClassifier::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let t2s = load_conversion(path, "t2s")?;
+        let s2t = load_conversion(path, "s2t")?;
         let mut posts = Vec::new();
         for (name, tag) in [("ns.bin", "ns"), ("idiom.bin", "i")] {
             match File::open(path.join(name)) {
                 Ok(mut file) => {
-                    posts.push(PostProcessor::new(Dat::load(&mut file)?, tag.into()));
+                    let post = PostProcessor::new(Dat::load(&mut file)?, tag.into());
+                    posts.push(Arc::new(post));
                 }
                 Err(e) if e.kind() == ErrorKind::NotFound => (),
-                Err(e) => return Err(e),
+                Err(e) => return Err(e.into()),
             }
         }
+        let post_ids = (0..posts.len() as u64).map(PostProcessorId).collect();
         Ok(Self {
-            label,
-            model,
-            dat,
-            t2s,
+            label: Arc::new(label),
+            model: Arc::new(model),
+            dat: Arc::new(dat),
+            chars: Arc::new(chars),
+            normalization: Normalization::None,
+            t2s: t2s.map(Arc::new),
+            s2t: s2t.map(Arc::new),
+            splitters: Vec::new(),
+            next_post_id: posts.len() as u64,
             posts,
+            post_ids,
+            rules: Vec::new(),
+            filter: None,
+            recognizers: vec![
+                Arc::new(PatternRecognizer::web()),
+                Arc::new(NumeralRecognizer::new()),
+                Arc::new(EmojiRecognizer::new()),
+            ],
         })
     }
-    pub fn add_postprocessor(&mut self, post: PostProcessor) {
-        self.posts.push(post);
+    /// Adds a splitter, run on the words of the model before
+    /// post-processors.
+    pub fn add_splitter(&mut self, splitter: Splitter) {
+        self.splitters.push(Arc::new(splitter));
+    }
+    /// Adds a post-processor and returns its id. Overlapping entries of
+    /// post-processors are resolved by their priorities, see
+    /// [`PostProcessor`]. The dictionaries of the model directory, if any,
+    /// come first.
+    pub fn add_postprocessor(&mut self, post: PostProcessor) -> PostProcessorId {
+        let id = PostProcessorId(self.next_post_id);
+        self.next_post_id += 1;
+        self.posts.push(Arc::new(post));
+        self.post_ids.push(id);
+        id
+    }
+    /// The post-processors with their ids, in the order they were added.
+    pub fn postprocessors(&self) -> impl Iterator<Item = (PostProcessorId, &PostProcessor)> {
+        self.post_ids
+            .iter()
+            .copied()
+            .zip(self.posts.iter().map(|x| &**x))
+    }
+    /// Replaces the post-processor `id` and returns the old one, as when a
+    /// user dictionary is reloaded, or `None` if there is no such
+    /// post-processor.
+    pub fn replace_postprocessor(
+        &mut self,
+        id: PostProcessorId,
+        post: PostProcessor,
+    ) -> Option<Arc<PostProcessor>> {
+        self.replace_postprocessor_arc(id, Arc::new(post))
+    }
+    fn replace_postprocessor_arc(
+        &mut self,
+        id: PostProcessorId,
+        post: Arc<PostProcessor>,
+    ) -> Option<Arc<PostProcessor>> {
+        let index = self.post_ids.iter().position(|x| *x == id)?;
+        Some(std::mem::replace(&mut self.posts[index], post))
+    }
+    /// Removes the post-processor `id` and returns it, or `None` if there is
+    /// no such post-processor.
+    pub fn remove_postprocessor(&mut self, id: PostProcessorId) -> Option<Arc<PostProcessor>> {
+        let index = self.post_ids.iter().position(|x| *x == id)?;
+        self.post_ids.remove(index);
+        Some(self.posts.remove(index))
+    }
+    /// Adds rules run on the words after post-processors.
+    pub fn add_rules(&mut self, rules: RuleSet) {
+        self.rules.push(Arc::new(rules));
+    }
+    pub fn filter(&self) -> Option<&Filter> {
+        self.filter.as_deref()
+    }
+    /// Sets the filter run last, dropping stopwords and whitespace so the
+    /// words no longer cover the raw text. No filter is set by default.
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        self.filter = filter.map(Arc::new);
+    }
+    /// Adds a recognizer run on the raw text before segmentation. By default,
+    /// [`PatternRecognizer::web`], [`NumeralRecognizer`] and
+    /// [`EmojiRecognizer`] are run; [`PatternRecognizer::hole`] is not, as
+    /// its pseudonyms are common English names.
+    pub fn add_recognizer(&mut self, recognizer: Box<dyn Recognizer>) {
+        self.recognizers.push(Arc::from(recognizer));
+    }
+    /// Removes all recognizers, including the default ones.
+    pub fn clear_recognizers(&mut self) {
+        self.recognizers.clear();
+    }
+    pub fn classifier(&self) -> &CharClassifier {
+        &self.chars
+    }
+    /// Replaces the character classes loaded from `chars.txt`, or the
+    /// default ones.
+    pub fn set_classifier(&mut self, chars: CharClassifier) {
+        self.chars = Arc::new(chars);
+    }
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+    /// Sets how the raw text is normalized before segmentation. Defaults to
+    /// [`Normalization::None`].
+    pub fn set_normalization(&mut self, normalization: Normalization) {
+        self.normalization = normalization;
     }
-    pub fn preprocess<'a>(&self, raw: &'a str) -> Preprocess<'a> {
-        let (input, pocs) = if let Some(t2s) = self.t2s.as_ref() {
-            Poc::build(t2s.process(raw.chars()))
+    pub fn t2s(&self) -> Option<&T2S> {
+        self.t2s.as_deref()
+    }
+    /// Replaces the traditional to simplified Chinese conversion loaded from
+    /// `t2s.bin`, or disables it with `None`.
+    pub fn set_t2s(&mut self, t2s: Option<T2S>) {
+        self.t2s = t2s.map(Arc::new);
+    }
+    /// The simplified to traditional Chinese conversion loaded from
+    /// `s2t.bin` and `s2t_phrases.txt`, for showing words in traditional
+    /// script with [`T2S::convert_str`]. Unlike [`Thulac::t2s`], it is
+    /// never applied to the input.
+    pub fn s2t(&self) -> Option<&S2T> {
+        self.s2t.as_deref()
+    }
+    pub fn set_s2t(&mut self, s2t: Option<S2T>) {
+        self.s2t = s2t.map(Arc::new);
+    }
+    pub fn preprocess<'a, 't>(&'t self, raw: &'a str) -> Preprocess<'a, 't> {
+        let mut chars = Vec::new();
+        self.normalization.apply(raw, &mut chars);
+        if let Some(t2s) = self.t2s.as_ref() {
+            chars = t2s.convert(&chars);
+        }
+        let (input, mut pocs) = Poc::build(chars.iter().map(|x| x.0), &self.chars);
+        // recognizers run on the converted text, with spaces
+        let normalized = if self.normalization == Normalization::None && self.t2s.is_none() {
+            Cow::Borrowed(raw)
         } else {
-            Poc::build(raw.chars())
+            Cow::Owned(chars.iter().map(|x| x.0).collect::<String>())
         };
-        Preprocess { raw, input, pocs }
+        let mut starts = Vec::with_capacity(chars.len() + 1);
+        let mut indices = Vec::with_capacity(chars.len() + 1);
+        let mut offsets = Vec::with_capacity(chars.len());
+        let mut start = 0;
+        for (ch, range) in chars {
+            starts.push(start);
+            indices.push(offsets.len());
+            start += ch.len_utf8();
+            if !self.chars.is_space(ch) {
+                offsets.push(range);
+            }
+        }
+        starts.push(start);
+        indices.push(offsets.len());
+        let mut start = 0;
+        for end in 1..=offsets.len() {
+            if end == offsets.len() || offsets[end] != offsets[start] {
+                Poc::join(&mut pocs, start..end);
+                start = end;
+            }
+        }
+        let mut spans = Vec::new();
+        for recognizer in self.recognizers.iter() {
+            recognizer.recognize(&normalized, &mut spans);
+        }
+        spans.retain(|(range, _)| {
+            !normalized[range.clone()]
+                .chars()
+                .any(|x| self.chars.is_space(x))
+        });
+        let spans = recognize::resolve(&normalized, spans)
+            .into_iter()
+            .map(|(range, tag)| {
+                let mut start = indices[starts.partition_point(|&x| x < range.start)];
+                let mut end = indices[starts.partition_point(|&x| x < range.end)];
+                // chars from the same grapheme cannot be split
+                while start > 0 && start < offsets.len() && offsets[start - 1] == offsets[start] {
+                    start -= 1;
+                }
+                while end > 0 && end < offsets.len() && offsets[end - 1] == offsets[end] {
+                    end += 1;
+                }
+                Poc::force_word(&mut pocs, start..end);
+                (start..end, tag)
+            })
+            .collect();
+        Preprocess {
+            raw,
+            input,
+            offsets,
+            pocs,
+            spans,
+        }
+    }
+    /// Same as [`Thulac::try_cut`], but panics if the segmentation fails.
+    pub fn cut<'a, 'b>(&'a self, preprocess: &'b Preprocess<'_, 'a>) -> Vec<Word<'b, 'a>> {
+        self.try_cut(preprocess).expect("failed to segment")
     }
-    pub fn cut<'a, 'b>(
+    pub fn try_cut<'a, 'b>(
         &'a self,
-        preprocess: &'b Preprocess<'_>,
-    ) -> Vec<(Range<usize>, &'b str, &'a str)> {
-        let Preprocess { raw, input, pocs } = preprocess;
-        let mut scores = self.model.init_scores(&self.dat, input, pocs.len());
+        preprocess: &'b Preprocess<'_, 'a>,
+    ) -> Result<Vec<Word<'b, 'a>>> {
+        self.try_cut_with(preprocess, &mut Scratch::default())
+    }
+    pub fn try_cut_with<'a, 'b>(
+        &'a self,
+        preprocess: &'b Preprocess<'_, 'a>,
+        scratch: &mut Scratch,
+    ) -> Result<Vec<Word<'b, 'a>>> {
+        let Preprocess {
+            raw,
+            input,
+            offsets,
+            pocs,
+            spans,
+        } = preprocess;
         let path = self
             .model
-            .decode(&mut scores, pocs, &self.label)
-            .expect("failed to segment");
+            .segment_with(&self.dat, input, pocs, &self.label, scratch)?;
+        if path.len() != offsets.len() {
+            return Err(Error::Misaligned);
+        }
+        let mut words = Vec::<(_, &'b str, &'a str)>::new();
         let mut last_raw = 0;
         let mut last_input = 0;
-        let mut input_chars = input.char_indices();
-        let (mut words, raw_chars) = path.iter().copied().fold(
-            (Vec::<(_, &'b str, &'a str)>::new(), raw.char_indices()),
-            move |(mut words, mut raw_chars), i| {
-                let (poc, desc) = self.label.label(i);
-                if raw_chars.next().unwrap().1.is_whitespace() {
-                    assert!(matches!(*poc, Poc::B | Poc::S));
-                    loop {
-                        let (next, next_ch) = raw_chars.next().unwrap();
-                        if !next_ch.is_whitespace() {
-                            words.push((last_raw..next, "", "w"));
-                            last_raw = next;
-                            break;
-                        }
-                    }
-                }
-                let _ = input_chars.next();
-                if matches!(*poc, Poc::E | Poc::S) {
-                    words.push((
-                        last_raw..raw_chars.offset(),
-                        &input[last_input..input_chars.offset()],
-                        &desc[..],
-                    ));
-                    last_raw = raw_chars.offset();
-                    last_input = input_chars.offset();
-                }
-                (words, raw_chars)
-            },
-        );
-        if raw_chars.offset() != raw.len() {
-            words.push((raw_chars.offset()..raw.len(), "", "w"));
+        let mut last_index = 0;
+        let mut spans = spans.iter().peekable();
+        let ends = input.char_indices().map(|(i, ch)| i + ch.len_utf8());
+        for (index, (i, end)) in path.iter().copied().zip(ends).enumerate() {
+            let (poc, desc) = self.label.label(i);
+            if !matches!(*poc, Poc::E | Poc::S) {
+                continue;
+            }
+            let range = offsets[last_index].start..offsets[index].end;
+            if range.start < last_raw {
+                return Err(Error::Misaligned);
+            }
+            // whitespace and dropped chars between words
+            if range.start != last_raw {
+                words.push((last_raw..range.start, "", "w"));
+            }
+            while spans.next_if(|(x, _)| x.start < last_index).is_some() {}
+            let tag = spans
+                .next_if(|(x, _)| *x == (last_index..index + 1))
+                .map_or(&desc[..], |(_, tag)| *tag);
+            last_raw = range.end;
+            words.push((range, &input[last_input..end], tag));
+            last_input = end;
+            last_index = index + 1;
+        }
+        if last_index != offsets.len() {
+            return Err(Error::Misaligned);
+        }
+        if last_raw != raw.len() {
+            words.push((last_raw..raw.len(), "", "w"));
+        }
+        for splitter in self.splitters.iter() {
+            words = splitter.split(words);
+        }
+        words = post::adjust_all(&self.posts, words);
+        for rules in self.rules.iter() {
+            words = rules.apply(words);
+        }
+        if let Some(filter) = self.filter.as_ref() {
+            words = filter.apply(words);
+        }
+        Ok(words)
+    }
+    /// Whether `word` is an entry of a dictionary: `ns.bin`, `idiom.bin` or
+    /// a user dictionary, as checked for the words found by [`Discovery`].
+    /// How the model segments `word` does not matter, since it often keeps
+    /// unseen words together. See [`Lexicon`] for the words of the model.
+    pub fn knows(&self, word: &str) -> bool {
+        self.posts.iter().any(|x| x.contains(word))
+    }
+    /// Segments every text in `raws` and returns the results in the same
+    /// order. With the `rayon` feature, texts are segmented in parallel on
+    /// the current thread pool, each worker thread reusing its own [`Scratch`].
+    #[allow(clippy::type_complexity)]
+    pub fn cut_batch<'a>(
+        &'a self,
+        raws: &[&str],
+    ) -> Vec<Result<Vec<(Range<usize>, String, &'a str)>>> {
+        let cut = |scratch: &mut Scratch, raw: &&str| {
+            let preprocess = self.preprocess(raw);
+            self.try_cut_with(&preprocess, scratch).map(|words| {
+                words
+                    .into_iter()
+                    .map(|(range, word, tag)| (range, word.to_owned(), tag))
+                    .collect()
+            })
+        };
+        #[cfg(feature = "rayon")]
+        {
+            raws.par_iter().map_init(Scratch::default, cut).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            let mut scratch = Scratch::default();
+            raws.iter().map(|raw| cut(&mut scratch, raw)).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// The raw text and words of `gold`, in `word_tag` format. Words are
+    /// slices of the raw text, which is leaked, so they can be merged like
+    /// those of [`Thulac::cut`].
+    pub(crate) fn gold(gold: &str) -> (&'static str, Vec<Word<'static, '_>>) {
+        let (raw, words) = parse_gold(gold, "_").unwrap();
+        let raw: &'static str = Box::leak(raw.into_boxed_str());
+        let words = words
+            .into_iter()
+            .map(|(range, _, tag)| (range.clone(), &raw[range], tag))
+            .collect();
+        (raw, words)
+    }
+
+    /// A model with all-zero weights over the bundled labels.
+    pub(crate) fn dummy() -> Thulac {
+        let label = Label::load(&mut include_str!("../model/label.txt").as_bytes()).unwrap();
+        let l_size = include_str!("../model/label.txt").lines().count();
+        let mut bytes = Vec::new();
+        bytes.extend((l_size as u32).to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.resize(bytes.len() + (l_size + 1) * l_size * 4, 0);
+        Thulac {
+            label: Arc::new(label),
+            model: Arc::new(Model::load(&mut Cursor::new(bytes)).unwrap()),
+            dat: Arc::new(Dat::build(vec![("\0", 0)])),
+            chars: Arc::new(CharClassifier::default()),
+            normalization: Normalization::None,
+            t2s: None,
+            s2t: None,
+            splitters: Vec::new(),
+            posts: Vec::new(),
+            post_ids: Vec::new(),
+            next_post_id: 0,
+            rules: Vec::new(),
+            filter: None,
+            recognizers: vec![Arc::new(PatternRecognizer::web())],
+        }
+    }
+
+    #[test]
+    fn test_cut_regressions() {
+        let mut custom = dummy();
+        let config = "space \\u{200b}\nothers \\u{3000}";
+        custom.set_classifier(CharClassifier::load_txt(&mut config.as_bytes()).unwrap());
+        let mut normalized = dummy();
+        normalized.set_normalization(Normalization::Casefold);
+        for (thulac, raw) in [dummy(), custom, normalized].iter().flat_map(|x| {
+            [
+                "",
+                " ",
+                "\u{3000}",
+                " \t\n",
+                "a b",
+                "你好 ",
+                "  你好",
+                "e\u{301}",
+                "\u{85}x\u{2028}",
+                "(´･ω･`)",
+                "https://x.com/a?b=1",
+                "你\u{200b}好\u{3000}",
+                "㈠ﬁ\u{200b}①Ｘ❤\u{fe0f}",
+            ]
+            .map(|raw| (x, raw))
+        }) {
+            let preprocess = thulac.preprocess(raw);
+            let words = thulac.try_cut(&preprocess).unwrap();
+            assert_eq!(words.first().map_or(0, |x| x.0.start), 0);
+            assert_eq!(words.last().map_or(0, |x| x.0.end), raw.len());
+            assert!(words.windows(2).all(|x| x[0].0.end == x[1].0.start));
         }
-        for post in self.posts.iter() {
-            words = post.adjust(words);
+    }
+
+    #[test]
+    fn test_recognizers() {
+        let thulac = dummy();
+        let raw = "看 https://x.com/a?b=1。发邮件到foo@bar.com";
+        let preprocess = thulac.preprocess(raw);
+        let words = thulac.cut(&preprocess);
+        assert!(words.contains(&(4..23, "https://x.com/a?b=1", "url")));
+        assert!(words.contains(&(38..49, "foo@bar.com", "email")));
+
+        let mut thulac = dummy();
+        thulac.clear_recognizers();
+        let preprocess = thulac.preprocess(raw);
+        let words = thulac.cut(&preprocess);
+        assert!(!words.iter().any(|x| x.2 == "url" || x.2 == "email"));
+    }
+
+    #[test]
+    fn test_normalization() {
+        let mut thulac = dummy();
+        thulac.set_normalization(Normalization::Nfkc);
+        let raw = "发邮件到ｆｏｏ@ｂａｒ.ｃｏｍ ㈠";
+        let preprocess = thulac.preprocess(raw);
+        let words = thulac.cut(&preprocess);
+        assert!(words.contains(&(12..41, "foo@bar.com", "email")));
+        assert_eq!(
+            words.last().map(|x| (x.0.clone(), x.1)),
+            Some((42..45, "(一)"))
+        );
+    }
+
+    #[test]
+    fn test_normalization_emoji() {
+        let mut thulac = dummy();
+        thulac.add_recognizer(Box::new(EmojiRecognizer::new()));
+        thulac.set_normalization(Normalization::Nfkc);
+        let raw = "我家👨\u{200d}👩\u{200d}👧";
+        let preprocess = thulac.preprocess(raw);
+        let words = thulac.cut(&preprocess);
+        // the joiners are kept, so the family is one emoji
+        assert_eq!(
+            words.last(),
+            Some(&(6..raw.len(), "👨\u{200d}👩\u{200d}👧", "x"))
+        );
+    }
+
+    #[test]
+    fn test_load_conversion() {
+        let dir = std::env::temp_dir().join(format!("thulac-s2t-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(load_conversion(&dir, "s2t").unwrap().is_none());
+        let s2t = T2S::load_txt(&mut "体\t體\n计算机\t電腦\n".as_bytes()).unwrap();
+        s2t.save(&mut File::create(dir.join("s2t.bin")).unwrap())
+            .unwrap();
+        s2t.save_phrases_txt(&mut File::create(dir.join("s2t_phrases.txt")).unwrap())
+            .unwrap();
+        let loaded = load_conversion(&dir, "s2t").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let mut thulac = dummy();
+        thulac.set_s2t(loaded);
+        assert_eq!(thulac.s2t().unwrap().convert_str("计算机体"), "電腦體");
+        assert!(thulac.t2s().is_none());
+    }
+
+    #[test]
+    fn test_t2s_phrases() {
+        let mut thulac = dummy();
+        let mut t2s = T2S::load(&mut Cursor::new(
+            [('體' as u32), ('体' as u32)]
+                .iter()
+                .flat_map(|x| x.to_ne_bytes())
+                .collect::<Vec<_>>(),
+        ))
+        .unwrap();
+        t2s.set_phrases([("電腦程式".to_owned(), "计算机程序".to_owned())]);
+        thulac.set_t2s(Some(t2s));
+        let raw = "電腦程式 體";
+        let preprocess = thulac.preprocess(raw);
+        let words = thulac.cut(&preprocess);
+        assert_eq!(words[0].0, 0..12);
+        assert_eq!(words[0].1, "计算机程序");
+        assert_eq!(
+            words.last().map(|x| (x.0.clone(), x.1)),
+            Some((13..16, "体"))
+        );
+    }
+
+    #[test]
+    fn test_replace_postprocessor() {
+        let tags = |thulac: &Thulac| {
+            let preprocess = thulac.preprocess("清华大学");
+            let words = thulac.cut(&preprocess);
+            words.iter().map(|x| x.2.to_owned()).collect::<Vec<_>>()
+        };
+        let mut thulac = dummy();
+        let before = tags(&thulac);
+        let dict = |x: &str| PostProcessor::load_txt(&mut x.as_bytes(), "uw".into()).unwrap();
+        let first = thulac.add_postprocessor(dict("北京大学\tni"));
+        let id = thulac.add_postprocessor(dict("清华大学\tni"));
+        assert_eq!(tags(&thulac), ["ni"]);
+        assert!(thulac.remove_postprocessor(first).is_some());
+        assert!(thulac.remove_postprocessor(first).is_none());
+        let old = thulac.clone();
+        assert!(thulac
+            .replace_postprocessor(id, dict("清华大学\tnt"))
+            .is_some());
+        assert_eq!(tags(&thulac), ["nt"]);
+        assert_eq!(tags(&old), ["ni"]);
+        assert!(thulac
+            .replace_postprocessor(first, dict("清华大学\tns"))
+            .is_none());
+        thulac.remove_postprocessor(id);
+        assert_eq!(thulac.postprocessors().count(), 0);
+        assert_eq!(tags(&thulac), before);
+    }
+
+    #[test]
+    fn test_knows() {
+        let mut thulac = dummy();
+        let mut slang = PatternRecognizer::new();
+        slang.add("绝绝子", "nz".into()).unwrap();
+        thulac.add_recognizer(Box::new(slang));
+        let preprocess = thulac.preprocess("绝绝子");
+        assert_eq!(thulac.cut(&preprocess), [(0..9, "绝绝子", "nz")]);
+        assert!(!thulac.knows("绝绝子"));
+        let dict = PostProcessor::load_txt(&mut "绝绝子\tnz".as_bytes(), "uw".into()).unwrap();
+        thulac.add_postprocessor(dict);
+        assert!(thulac.knows("绝绝子"));
+        assert!(!thulac.knows("绝绝"));
+    }
+
+    #[test]
+    fn test_cut_batch() {
+        let thulac = std::sync::Arc::new(dummy());
+        let raws = (0..64)
+            .map(|i| format!("第{}个句子， hello world！", i))
+            .collect::<Vec<_>>();
+        let raws = raws.iter().map(String::as_str).collect::<Vec<_>>();
+        let expected = raws
+            .iter()
+            .map(|raw| {
+                let preprocess = thulac.preprocess(raw);
+                let words = thulac.cut(&preprocess);
+                words
+                    .into_iter()
+                    .map(|(range, word, tag)| (range, word.to_owned(), tag.to_owned()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let handles = (0..4)
+            .map(|_| {
+                let thulac = thulac.clone();
+                let raws = raws.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+                std::thread::spawn(move || {
+                    let raws = raws.iter().map(String::as_str).collect::<Vec<_>>();
+                    thulac
+                        .cut_batch(&raws)
+                        .into_iter()
+                        .map(|words| {
+                            words
+                                .unwrap()
+                                .into_iter()
+                                .map(|(range, word, tag)| (range, word, tag.to_owned()))
+                                .collect::<Vec<_>>()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
         }
-        words
     }
 }
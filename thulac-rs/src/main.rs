@@ -1,36 +1,745 @@
-#![feature(stdin_forwarders)]
-#![feature(result_flattening)]
-
 use std::{
-    env,
     fs::File,
-    io::{stdin, BufReader, ErrorKind},
-    path::PathBuf,
+    io::{stdin, stdout, BufRead, BufReader, BufWriter, Result, Write},
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::Arc,
+    thread,
+    time::Duration,
 };
 
-use thulac_rs::{Dat, PostProcessor, Thulac};
+use clap::{Args, Parser, Subcommand};
+use thulac_rs::{
+    evaluate, CharClassifier, Dat, Discovery, Filter, Format, Formatter, Idf, IdfBuilder,
+    KeywordExtractor, Lexicon, Normalization, OovAnalyzer, PatternRecognizer, PostProcessor,
+    PostProcessorId, RuleSet, SharedThulac, Splitter, Thulac, T2S,
+};
 
-fn main() {
-    let path = PathBuf::from_str(
-        &env::var("THULAC_MODEL_PATH").expect("failed to fetch env THULAC_MODEL_PATH"),
+/// Number of lines segmented together when running on several threads.
+const BATCH_SIZE: usize = 1024;
+
+/// How often user dictionaries are checked for changes with `--watch`.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Parser)]
+#[command(about = "Chinese word segmentation and POS tagging")]
+struct Cli {
+    #[command(flatten)]
+    model: ModelArgs,
+    #[command(flatten)]
+    segment: SegmentArgs,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compare the segmentation of a gold corpus in `word_tag` format with
+    /// the output of the model
+    Eval(EvalArgs),
+    /// Compile tab-separated conversion tables, like OpenCC's
+    /// TSCharacters.txt and TSPhrases.txt, into t2s.bin and t2s_phrases.txt
+    CompileT2s(CompileT2sArgs),
+    /// Extract the keywords of each line, or build an IDF table from a
+    /// corpus of one document per line
+    Keywords(KeywordsArgs),
+    /// Find new words in a corpus, writing those missing from the
+    /// dictionaries and lexicons one per line, most frequent first
+    Discover(DiscoverArgs),
+    /// Summarize the words of the input missing from a lexicon, most
+    /// frequent first
+    Oov(OovArgs),
+}
+
+#[derive(Args)]
+struct ModelArgs {
+    /// Directory containing label.txt, model.bin, dat.bin and the optional
+    /// chars.txt, t2s.bin, t2s_phrases.txt, s2t.bin, s2t_phrases.txt, ns.bin
+    /// and idiom.bin
+    #[arg(
+        short,
+        long,
+        alias = "model_dir",
+        env = "THULAC_MODEL_PATH",
+        global = true
+    )]
+    model: Option<PathBuf>,
+    /// User dictionary of `word[<TAB>tag[<TAB>priority]]` lines; words
+    /// without a tag are tagged TAG (`uw` by default); overlapping words
+    /// of several dictionaries are resolved by priority; may be given
+    /// several times
+    #[arg(
+        short,
+        long = "user-dict",
+        alias = "user",
+        value_name = "PATH[:TAG]",
+        global = true
+    )]
+    user_dicts: Vec<UserDict>,
+    /// File of `compound<TAB>part part` lines and boundary patterns like
+    /// `left|right`, splitting words merged wrongly by the model; may be
+    /// given several times
+    #[arg(long = "split-dict", value_name = "PATH", global = true)]
+    split_dicts: Vec<PathBuf>,
+    /// File of `pattern<TAB>action` rules merging, splitting or retagging
+    /// words after user dictionaries; may be given several times
+    #[arg(long, value_name = "PATH", global = true)]
+    rules: Vec<PathBuf>,
+    /// File of `tag<TAB>regex` lines; matches are kept as single words with
+    /// the tag; may be given several times
+    #[arg(long, global = true)]
+    patterns: Vec<PathBuf>,
+    /// File of `class chars` lines moving characters to the `space`,
+    /// `single`, `multi` or `others` class; overrides chars.txt
+    #[arg(long, value_name = "PATH", global = true)]
+    chars: Option<PathBuf>,
+    /// Normalize the text before segmentation: none, nfkc, or casefold for
+    /// NFKC and lowercasing
+    #[arg(long, default_value = "none", global = true)]
+    normalize: Normalization,
+    /// Recognize tree-hole post references like `#123456` and reply names
+    /// like `[Alice]` or `Re Bob:`
+    #[arg(long, global = true)]
+    hole: bool,
+    /// Convert traditional Chinese to simplified, requires t2s.bin (default
+    /// when t2s.bin exists)
+    #[arg(long, conflicts_with = "no_t2s", global = true)]
+    t2s: bool,
+    /// Do not convert traditional Chinese to simplified
+    #[arg(long, global = true)]
+    no_t2s: bool,
+}
+
+#[derive(Args)]
+struct SegmentArgs {
+    /// Read from this file instead of stdin
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+    /// Write to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Only segment, without tags; same as `--format seg`
+    #[arg(long, alias = "seg_only")]
+    seg_only: bool,
+    /// Drop whitespace, words tagged with one of `--filter-tags` or not with
+    /// one of `--keep-tags`, and stopwords
+    #[arg(long)]
+    filter: bool,
+    /// Tags dropped by `--filter`
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "w,u",
+        requires = "filter"
+    )]
+    filter_tags: Vec<String>,
+    /// Tags kept by `--filter`, dropping all others
+    #[arg(long, value_delimiter = ',', requires = "filter")]
+    keep_tags: Vec<String>,
+    /// File with one word per line dropped by `--filter`, instead of the
+    /// bundled Chinese stopwords
+    #[arg(long, requires = "filter")]
+    stopwords: Option<PathBuf>,
+    /// Number of threads segmenting lines in parallel
+    #[arg(short, long, default_value_t = 1)]
+    threads: usize,
+    /// Output format: thulac, seg, json, conll or tsv
+    #[arg(short, long, default_value = "thulac")]
+    format: Format,
+    /// Separator between word and tag in the thulac format
+    #[arg(long, alias = "deli", default_value = "_")]
+    separator: String,
+    /// Reload user dictionaries when their files change, without
+    /// restarting; lines already being segmented keep the old dictionaries
+    #[arg(long)]
+    watch: bool,
+    /// Write words in traditional Chinese, converted with s2t.bin from the
+    /// model directory
+    #[arg(long)]
+    traditional: bool,
+}
+
+#[derive(Args)]
+struct EvalArgs {
+    /// Gold corpus, one sentence of `word_tag` pairs separated by spaces per
+    /// line
+    gold: PathBuf,
+    /// Dictionary of the training data, one word per line, to compute the
+    /// recall of out-of-vocabulary words
+    #[arg(long)]
+    dict: Option<PathBuf>,
+    /// Separator between word and tag in the gold corpus
+    #[arg(long, default_value = "_")]
+    separator: String,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct KeywordsArgs {
+    /// Read from this file instead of stdin
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+    /// Count the documents of the input and write an IDF table to this
+    /// file instead of extracting keywords
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["idf", "textrank"])]
+    build_idf: Option<PathBuf>,
+    /// Leave out of the IDF table words found in fewer documents
+    #[arg(long, default_value_t = 2, requires = "build_idf")]
+    min_count: usize,
+    /// IDF table weighting keywords by TF-IDF; keywords are ranked by term
+    /// frequency without it
+    #[arg(long, value_name = "PATH")]
+    idf: Option<PathBuf>,
+    /// Rank keywords by TextRank instead of TF-IDF
+    #[arg(long)]
+    textrank: bool,
+    /// Number of keywords of each line
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+#[derive(Args)]
+struct DiscoverArgs {
+    /// Read from this file instead of stdin
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+    /// Write to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Words listed in this file, one per line, are not new; may be given
+    /// several times
+    #[arg(long, value_name = "PATH")]
+    lexicon: Vec<PathBuf>,
+    /// Also leave out words whose chars and pairs of chars all occur in the
+    /// training data of the model; this drops many new words made of common
+    /// chars
+    #[arg(long)]
+    model_features: bool,
+    /// Maximum number of chars of a word
+    #[arg(long, default_value_t = 4)]
+    max_len: usize,
+    /// Minimum number of occurrences of a word
+    #[arg(long, default_value_t = 5)]
+    min_count: usize,
+    /// Minimum pointwise mutual information between the parts of a word
+    #[arg(long, default_value_t = 3.0)]
+    min_pmi: f64,
+    /// Minimum branching entropy of the chars on each side of a word
+    #[arg(long, default_value_t = 1.0)]
+    min_entropy: f64,
+    /// Append the count, PMI and left and right entropies to each word,
+    /// separated by tabs
+    #[arg(long)]
+    stats: bool,
+}
+
+#[derive(Args)]
+struct OovArgs {
+    /// Read from this file instead of stdin
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+    /// Lexicon of one word per line, such as the words of the training
+    /// data; without it, words whose chars never occurred together in the
+    /// training data are out of vocabulary
+    #[arg(long, value_name = "PATH")]
+    lexicon: Option<PathBuf>,
+    /// Number of out-of-vocabulary words listed
+    #[arg(long, default_value_t = 50)]
+    top: usize,
+}
+
+#[derive(Args)]
+struct CompileT2sArgs {
+    /// Files of `source<TAB>target` lines, chars and phrases alike
+    #[arg(required = true)]
+    tables: Vec<PathBuf>,
+    /// Directory to write to, usually the model directory
+    #[arg(short, long = "output-dir", default_value = ".")]
+    output_dir: PathBuf,
+    /// The tables convert simplified to traditional; write s2t.bin and
+    /// s2t_phrases.txt instead
+    #[arg(long)]
+    s2t: bool,
+    /// Swap the columns of the tables
+    #[arg(long)]
+    reverse: bool,
+}
+
+#[derive(Clone)]
+struct UserDict {
+    path: PathBuf,
+    tag: String,
+}
+
+impl FromStr for UserDict {
+    type Err = String;
+
+    /// Parses `PATH[:TAG]`. The text after the last `:` is a tag only if it
+    /// is made of ASCII letters, digits and `_`, so paths like
+    /// `C:\dicts\a.txt` or `./dir:v2/words.txt` are kept whole.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let is_tag =
+            |x: &str| !x.is_empty() && x.chars().all(|x| x.is_ascii_alphanumeric() || x == '_');
+        match s.rsplit_once(':') {
+            Some((path, tag)) if !path.is_empty() && is_tag(tag) => Ok(Self {
+                path: path.into(),
+                tag: tag.into(),
+            }),
+            _ => Ok(Self {
+                path: s.into(),
+                tag: "uw".into(),
+            }),
+        }
+    }
+}
+
+/// Loads the model with the dictionaries given, returning the user
+/// dictionaries along with the indices of their post-processors.
+fn load(args: ModelArgs) -> (Thulac, Vec<(UserDict, PostProcessorId)>) {
+    let path = args
+        .model
+        .expect("--model or env THULAC_MODEL_PATH is required");
+    let mut thulac = Thulac::load(path).expect("failed to load model");
+    if args.no_t2s {
+        thulac.set_t2s(None);
+    } else if args.t2s && thulac.t2s().is_none() {
+        panic!("--t2s requires t2s.bin in the model directory");
+    }
+    thulac.set_normalization(args.normalize);
+    if let Some(path) = args.chars {
+        let chars = File::open(&path)
+            .map_err(Into::into)
+            .and_then(|x| CharClassifier::load_txt(&mut BufReader::new(x)))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+        thulac.set_classifier(chars);
+    }
+    for path in args.split_dicts {
+        let splitter = File::open(&path)
+            .and_then(|x| Splitter::load_txt(&mut BufReader::new(x)))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+        thulac.add_splitter(splitter);
+    }
+    let mut user_dicts = Vec::new();
+    for dict in args.user_dicts {
+        let post = File::open(&dict.path)
+            .and_then(|x| PostProcessor::load_txt(&mut BufReader::new(x), dict.tag.clone()))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", dict.path.display(), e));
+        user_dicts.push((dict, thulac.add_postprocessor(post)));
+    }
+    for path in args.rules {
+        let rules = File::open(&path)
+            .map_err(Into::into)
+            .and_then(|x| RuleSet::load_txt(&mut BufReader::new(x)))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+        thulac.add_rules(rules);
+    }
+    if args.hole {
+        thulac.add_recognizer(Box::new(PatternRecognizer::hole()));
+    }
+    for path in args.patterns {
+        let recognizer = File::open(&path)
+            .map_err(Into::into)
+            .and_then(|x| PatternRecognizer::load_txt(&mut BufReader::new(x)))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+        thulac.add_recognizer(Box::new(recognizer));
+    }
+    (thulac, user_dicts)
+}
+
+/// Checks the user dictionaries for changes forever. The dictionaries that
+/// changed are reloaded into a copy of the segmenter, which replaces it. A
+/// dictionary failing to load is reported and left as it was.
+fn watch(thulac: &SharedThulac, user_dicts: &[(UserDict, PostProcessorId)]) {
+    let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|x| x.modified()).ok();
+    let mut times = user_dicts
+        .iter()
+        .map(|(dict, _)| modified(&dict.path))
+        .collect::<Vec<_>>();
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        for ((UserDict { path, tag }, id), time) in user_dicts.iter().zip(times.iter_mut()) {
+            let current = modified(path);
+            if current == *time {
+                continue;
+            }
+            *time = current;
+            match File::open(path)
+                .and_then(|x| PostProcessor::load_txt(&mut BufReader::new(x), tag.clone()))
+            {
+                Ok(post) => {
+                    thulac.replace_postprocessor(*id, post);
+                    eprintln!("reloaded {}", path.display());
+                }
+                Err(e) => eprintln!("failed to reload {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+fn segment(mut thulac: Thulac, user_dicts: Vec<(UserDict, PostProcessorId)>, args: SegmentArgs) {
+    if args.filter {
+        let stopwords = match args.stopwords {
+            Some(path) => load_words(&path),
+            None => Filter::default_stopwords(),
+        };
+        let mut filter = Filter::new()
+            .deny_tags(args.filter_tags)
+            .with_stopwords(stopwords);
+        if !args.keep_tags.is_empty() {
+            filter = filter.allow_tags(args.keep_tags);
+        }
+        thulac.set_filter(Some(filter));
+    }
+    if args.traditional && thulac.s2t().is_none() {
+        eprintln!("error: --traditional requires s2t.bin in the model directory");
+        std::process::exit(2);
+    }
+    let thulac = Arc::new(SharedThulac::new(thulac));
+    if args.watch && !user_dicts.is_empty() {
+        let thulac = thulac.clone();
+        thread::spawn(move || watch(&thulac, &user_dicts));
+    }
+    #[cfg(feature = "rayon")]
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads)
+        .build_global()
+        .expect("failed to start threads");
+    #[cfg(not(feature = "rayon"))]
+    if args.threads > 1 {
+        eprintln!("built without the rayon feature, ignoring --threads");
+    }
+
+    let format = if args.seg_only {
+        Format::Seg
+    } else {
+        args.format
+    };
+    let formatter = Formatter::new(format, args.separator);
+    let input: Box<dyn BufRead> = match &args.input {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).expect("failed to open input"),
+        )),
+        None => Box::new(stdin().lock()),
+    };
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).expect("failed to create output"),
+        )),
+        None => Box::new(stdout().lock()),
+    };
+    let batch_size = if args.threads > 1 { BATCH_SIZE } else { 1 };
+    let mut lines = input.lines();
+    loop {
+        let batch = lines
+            .by_ref()
+            .take(batch_size)
+            .collect::<Result<Vec<_>>>()
+            .expect("failed to read line");
+        if batch.is_empty() {
+            break;
+        }
+        let raws = batch.iter().map(String::as_str).collect::<Vec<_>>();
+        let thulac = thulac.load();
+        for (line, result) in batch.iter().zip(thulac.cut_batch(&raws)) {
+            match result {
+                Ok(words) if args.traditional => {
+                    let s2t = thulac.s2t().unwrap();
+                    let words = words
+                        .into_iter()
+                        .map(|(range, word, tag)| (range, s2t.convert_str(&word), tag))
+                        .collect::<Vec<_>>();
+                    formatter
+                        .write(&mut output, line, &words)
+                        .expect("failed to write output");
+                }
+                Ok(words) => {
+                    formatter
+                        .write(&mut output, line, &words)
+                        .expect("failed to write output");
+                }
+                Err(e) => eprintln!("failed to segment {:?}: {}", line, e),
+            }
+        }
+    }
+    output.flush().expect("failed to write output");
+}
+
+/// Loads a file of one word per line as a set loaded with `insert_end`.
+fn load_words(path: &Path) -> Dat {
+    File::open(path)
+        .and_then(|x| Dat::load_set_txt(&mut BufReader::new(x), true))
+        .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e))
+}
+
+fn eval(thulac: Thulac, args: EvalArgs) {
+    let lexicon = args.dict.as_deref().map(load_words);
+    let gold = File::open(&args.gold).expect("failed to open gold corpus");
+    let evaluation = evaluate(
+        &thulac,
+        BufReader::new(gold),
+        &args.separator,
+        lexicon.as_ref(),
     )
-    .expect("invalid path");
-    let mut thulac = Thulac::load(&path).expect("failed to load model");
-    match File::open("user.txt")
-        .map(|x| Dat::load_set_txt(&mut BufReader::new(x), true))
-        .flatten()
-    {
-        Ok(dat) => {
-            thulac.add_postprocessor(PostProcessor::new(dat, "uw".into()));
-        }
-        Err(e) if e.kind() == ErrorKind::NotFound => (),
-        Err(e) => panic!("{}", e),
-    }
-    for line in stdin().lines() {
+    .expect("failed to evaluate");
+    if args.json {
+        serde_json::to_writer_pretty(stdout().lock(), &evaluation.report())
+            .expect("failed to write report");
+        println!();
+        return;
+    }
+    println!("sentences\t{}", evaluation.sentences);
+    println!(
+        "words\tgold {}\tpredicted {}\tcorrect {}",
+        evaluation.gold_words, evaluation.predicted_words, evaluation.correct_words
+    );
+    println!("precision\t{:.4}", evaluation.precision());
+    println!("recall\t{:.4}", evaluation.recall());
+    println!("f1\t{:.4}", evaluation.f1());
+    println!("pos accuracy\t{:.4}", evaluation.pos_accuracy());
+    println!("pos f1\t{:.4}", evaluation.pos_f1());
+    if let Some(oov_recall) = evaluation.oov_recall() {
+        println!(
+            "oov recall\t{:.4}\t({} words)",
+            oov_recall, evaluation.oov_words
+        );
+    }
+    println!();
+    println!("gold\tcount\taccuracy\tconfused with");
+    for (tag, predicted) in evaluation.confusion.iter() {
+        let count = predicted.values().sum::<usize>();
+        let correct = predicted.get(tag).copied().unwrap_or_default();
+        let mut confused = predicted
+            .iter()
+            .filter(|(x, _)| *x != tag)
+            .collect::<Vec<_>>();
+        confused.sort_by_key(|(_, &x)| std::cmp::Reverse(x));
+        let confused = confused
+            .iter()
+            .take(3)
+            .map(|(x, n)| format!("{}:{}", x, n))
+            .collect::<Vec<_>>();
+        println!(
+            "{}\t{}\t{:.4}\t{}",
+            tag,
+            count,
+            correct as f64 / count as f64,
+            confused.join(" ")
+        );
+    }
+}
+
+fn keywords(thulac: Thulac, args: KeywordsArgs) {
+    let input: Box<dyn BufRead> = match &args.input {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).expect("failed to open input"),
+        )),
+        None => Box::new(stdin().lock()),
+    };
+    let mut builder = IdfBuilder::new();
+    let mut extractor = KeywordExtractor::new();
+    if let Some(path) = args.idf {
+        let idf = File::open(&path)
+            .and_then(|mut x| Idf::load(&mut x))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+        extractor = extractor.with_idf(idf);
+    }
+    let mut output = stdout().lock();
+    for line in input.lines() {
         let line = line.expect("failed to read line");
         let preprocess = thulac.preprocess(&line);
-        let result = thulac.cut(&preprocess);
-        println!("{:?}", result);
+        let words = match thulac.try_cut(&preprocess) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("failed to segment {:?}: {}", line, e);
+                continue;
+            }
+        };
+        if args.build_idf.is_some() {
+            builder.add_document(words.iter().map(|x| x.1));
+            continue;
+        }
+        let keywords = if args.textrank {
+            extractor.textrank(&words, args.top)
+        } else {
+            extractor.tfidf(&words, args.top)
+        };
+        let keywords = keywords
+            .iter()
+            .map(|(word, score)| format!("{}:{:.4}", word, score))
+            .collect::<Vec<_>>();
+        writeln!(output, "{}", keywords.join(" ")).expect("failed to write output");
+    }
+    if let Some(path) = args.build_idf {
+        File::create(&path)
+            .map(BufWriter::new)
+            .and_then(|mut x| {
+                builder
+                    .build(args.min_count)
+                    .save(&mut x)
+                    .and_then(|_| x.flush())
+            })
+            .unwrap_or_else(|e| panic!("failed to write {}: {}", path.display(), e));
+    }
+}
+
+fn discover(thulac: Thulac, args: DiscoverArgs) {
+    let mut lexicons = args
+        .lexicon
+        .iter()
+        .map(|path| Lexicon::Words(load_words(path)))
+        .collect::<Vec<_>>();
+    if args.model_features {
+        lexicons.push(Lexicon::features(&thulac));
+    }
+    let input: Box<dyn BufRead> = match &args.input {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).expect("failed to open input"),
+        )),
+        None => Box::new(stdin().lock()),
+    };
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).expect("failed to create output"),
+        )),
+        None => Box::new(stdout().lock()),
+    };
+    let mut discovery = Discovery::new()
+        .with_max_len(args.max_len)
+        .with_min_count(args.min_count)
+        .with_min_pmi(args.min_pmi)
+        .with_min_entropy(args.min_entropy);
+    for line in input.lines() {
+        discovery.add_text(&line.expect("failed to read line"));
+    }
+    for candidate in discovery.candidates() {
+        if lexicons.iter().any(|x| x.contains(&candidate.word)) || thulac.knows(&candidate.word) {
+            continue;
+        }
+        if args.stats {
+            writeln!(
+                output,
+                "{}\t{}\t{:.4}\t{:.4}\t{:.4}",
+                candidate.word,
+                candidate.count,
+                candidate.pmi,
+                candidate.left_entropy,
+                candidate.right_entropy
+            )
+        } else {
+            writeln!(output, "{}", candidate.word)
+        }
+        .expect("failed to write output");
+    }
+    output.flush().expect("failed to write output");
+}
+
+fn oov(thulac: Thulac, args: OovArgs) {
+    let lexicon = match &args.lexicon {
+        Some(path) => Lexicon::Words(load_words(path)),
+        None => Lexicon::features(&thulac),
+    };
+    let input: Box<dyn BufRead> = match &args.input {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).expect("failed to open input"),
+        )),
+        None => Box::new(stdin().lock()),
+    };
+    let mut analyzer = OovAnalyzer::new(lexicon);
+    for line in input.lines() {
+        let line = line.expect("failed to read line");
+        let preprocess = thulac.preprocess(&line);
+        match thulac.try_cut(&preprocess) {
+            Ok(words) => {
+                analyzer.add(&words);
+            }
+            Err(e) => eprintln!("failed to segment {:?}: {}", line, e),
+        }
+    }
+    println!("words\t{}", analyzer.words());
+    println!(
+        "oov words\t{}\t({:.4})",
+        analyzer.oov_words(),
+        analyzer.oov_words() as f64 / analyzer.words().max(1) as f64
+    );
+    println!("distinct oov words\t{}", analyzer.distinct_oov_words());
+    println!();
+    println!("word\tcount");
+    for (word, count) in analyzer.top(args.top) {
+        println!("{}\t{}", word, count);
+    }
+}
+
+fn compile_t2s(args: CompileT2sArgs) {
+    let mut t2s = T2S::new();
+    for path in args.tables.iter() {
+        File::open(path)
+            .and_then(|x| t2s.extend_txt(&mut BufReader::new(x)))
+            .unwrap_or_else(|e| panic!("failed to load {}: {}", path.display(), e));
+    }
+    if args.reverse {
+        t2s = t2s.reversed();
+    }
+    let name = if args.s2t { "s2t" } else { "t2s" };
+    let bin = args.output_dir.join(format!("{}.bin", name));
+    File::create(&bin)
+        .map(BufWriter::new)
+        .and_then(|mut x| t2s.save(&mut x).and_then(|_| x.flush()))
+        .unwrap_or_else(|e| panic!("failed to write {}: {}", bin.display(), e));
+    let phrases = args.output_dir.join(format!("{}_phrases.txt", name));
+    File::create(&phrases)
+        .map(BufWriter::new)
+        .and_then(|mut x| t2s.save_phrases_txt(&mut x).and_then(|_| x.flush()))
+        .unwrap_or_else(|e| panic!("failed to write {}: {}", phrases.display(), e));
+}
+
+fn main() {
+    let cli = Cli::parse();
+    match cli.command {
+        None => {
+            let (thulac, user_dicts) = load(cli.model);
+            segment(thulac, user_dicts, cli.segment)
+        }
+        Some(Command::Eval(args)) => eval(load(cli.model).0, args),
+        Some(Command::CompileT2s(args)) => compile_t2s(args),
+        Some(Command::Keywords(args)) => keywords(load(cli.model).0, args),
+        Some(Command::Discover(args)) => discover(load(cli.model).0, args),
+        Some(Command::Oov(args)) => oov(load(cli.model).0, args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_user_dict() {
+        let dict = |s: &str| {
+            let dict = s.parse::<UserDict>().unwrap();
+            (dict.path.to_str().unwrap().to_owned(), dict.tag)
+        };
+        assert_eq!(dict("words.txt"), ("words.txt".into(), "uw".into()));
+        assert_eq!(dict("words.txt:ns"), ("words.txt".into(), "ns".into()));
+        assert_eq!(
+            dict("C:\\dicts\\a.txt"),
+            ("C:\\dicts\\a.txt".into(), "uw".into())
+        );
+        assert_eq!(
+            dict("C:\\dicts\\a.txt:np"),
+            ("C:\\dicts\\a.txt".into(), "np".into())
+        );
+        assert_eq!(
+            dict("./dir:v2/words.txt"),
+            ("./dir:v2/words.txt".into(), "uw".into())
+        );
+        assert_eq!(dict("words.txt:"), ("words.txt:".into(), "uw".into()));
     }
 }
@@ -1,12 +1,12 @@
-use ndarray::Array2;
+use ndarray::{Array2, ArrayViewMut2};
 use std::{
     io::{Read, Result},
     iter,
-    mem::size_of,
-    slice, usize,
+    mem::size_of_val,
+    slice,
 };
 
-use crate::{Dat, Label, Poc};
+use crate::{Dat, Error, Label, Poc};
 
 const SENTENCE_BOUNDARY: char = '#';
 const FEATURE_SEPARATOR: char = ' ';
@@ -35,6 +35,14 @@ pub struct Model {
     fl_weights: Array2<i32>,
 }
 
+/// Buffers reused by [`Model::segment_with`], so that a thread segmenting
+/// many sentences does not reallocate them for each one.
+#[derive(Default)]
+pub struct Scratch {
+    scores: Vec<i32>,
+    prev: Vec<usize>,
+}
+
 pub fn normalize_char(ch: char) -> char {
     let ord = ch as u32;
     if ord > 32 && ord < 128 {
@@ -44,6 +52,20 @@ pub fn normalize_char(ch: char) -> char {
     }
 }
 
+/// Whether every char of `word` and every pair of adjacent chars has
+/// features in `dat`, which means they occurred in the training data.
+pub(crate) fn has_features(dat: &Dat, word: &str) -> bool {
+    let chars = word.chars().map(normalize_char).collect::<Vec<_>>();
+    let has = |chars: &[char]| {
+        chars
+            .iter()
+            .chain([&FEATURE_SEPARATOR])
+            .try_fold(dat.root(), |node, &ch| dat.child(node, ch))
+            .is_some()
+    };
+    chars.windows(1).all(has) && chars.windows(2).all(has)
+}
+
 impl Model {
     pub fn load<R: Read>(reader: &mut R) -> Result<Model> {
         let mut buf = [0u8; 4];
@@ -56,16 +78,10 @@ impl Model {
         let ll_slice = ll_weights.as_slice_mut().unwrap();
         let fl_slice = fl_weights.as_slice_mut().unwrap();
         reader.read_exact(unsafe {
-            slice::from_raw_parts_mut(
-                ll_slice.as_mut_ptr() as *mut u8,
-                ll_slice.len() * size_of::<i32>(),
-            )
+            slice::from_raw_parts_mut(ll_slice.as_mut_ptr() as *mut u8, size_of_val(ll_slice))
         })?;
         reader.read_exact(unsafe {
-            slice::from_raw_parts_mut(
-                fl_slice.as_mut_ptr() as *mut u8,
-                fl_slice.len() * size_of::<i32>(),
-            )
+            slice::from_raw_parts_mut(fl_slice.as_mut_ptr() as *mut u8, size_of_val(fl_slice))
         })?;
         Ok(Model {
             ll_weights,
@@ -74,6 +90,12 @@ impl Model {
     }
 
     pub fn init_scores(&self, dat: &Dat, sentence: &str, sentence_len: usize) -> Array2<i32> {
+        let mut scores = Array2::<i32>::zeros((sentence_len, self.fl_weights.ncols()));
+        self.fill_scores(dat, sentence, scores.view_mut());
+        scores
+    }
+
+    fn fill_scores(&self, dat: &Dat, sentence: &str, mut scores: ArrayViewMut2<i32>) {
         let b = SENTENCE_BOUNDARY;
         let f = FEATURE_SEPARATOR;
         let mut chars = sentence
@@ -92,7 +114,6 @@ impl Model {
         let mut bi_ll = dat.and_child(dat.and_child(base_l, b), f);
         let mut bi_lm = dat.and_child(dat.and_child(base_l, ch_m), f);
         let mut bi_mr = dat.and_child(dat.and_child(base_m, ch_r), f);
-        let mut scores = Array2::<i32>::zeros((sentence_len, self.fl_weights.ncols()));
         for (i, ch) in chars.enumerate() {
             let mut score = scores.row_mut(i);
             let base_rr = dat.child(dat.root(), ch);
@@ -127,20 +148,55 @@ impl Model {
             bi_lm = bi_mr;
             bi_mr = bi_rr;
         }
-        scores
     }
 
+    /// The best label of each char, given the feature scores of each char and
+    /// its POC constraints. Fails with [`Error::Misaligned`] if `scores` and
+    /// `pocs` differ in length.
     pub fn decode(
         &self,
         scores: &mut Array2<i32>,
         pocs: &[Poc],
         label: &Label,
-    ) -> Option<Vec<usize>> {
-        assert_eq!(scores.nrows(), pocs.len());
+    ) -> crate::Result<Vec<usize>> {
+        let mut prev = Array2::<usize>::from_elem(scores.dim(), usize::MAX);
+        self.decode_into(scores.view_mut(), prev.view_mut(), pocs, label)
+    }
+
+    /// Same as [`Model::init_scores`] followed by [`Model::decode`], but
+    /// keeps its buffers in `scratch` for the next sentence.
+    pub fn segment_with(
+        &self,
+        dat: &Dat,
+        sentence: &str,
+        pocs: &[Poc],
+        label: &Label,
+        scratch: &mut Scratch,
+    ) -> crate::Result<Vec<usize>> {
+        let shape = (pocs.len(), self.fl_weights.ncols());
+        scratch.scores.clear();
+        scratch.scores.resize(shape.0 * shape.1, 0);
+        scratch.prev.clear();
+        scratch.prev.resize(shape.0 * shape.1, usize::MAX);
+        let mut scores = ArrayViewMut2::from_shape(shape, &mut scratch.scores[..]).unwrap();
+        let prev = ArrayViewMut2::from_shape(shape, &mut scratch.prev[..]).unwrap();
+        self.fill_scores(dat, sentence, scores.view_mut());
+        self.decode_into(scores, prev, pocs, label)
+    }
+
+    fn decode_into(
+        &self,
+        mut scores: ArrayViewMut2<i32>,
+        mut prev: ArrayViewMut2<usize>,
+        pocs: &[Poc],
+        label: &Label,
+    ) -> crate::Result<Vec<usize>> {
+        if scores.nrows() != pocs.len() || scores.ncols() != self.ll_weights.ncols() {
+            return Err(Error::Misaligned);
+        }
         if pocs.is_empty() {
-            return Some(Vec::new());
+            return Ok(Vec::new());
         }
-        let mut prev = Array2::<usize>::from_elem((scores.nrows(), scores.ncols()), usize::MAX);
         label
             .allowed_labels(*pocs.first().unwrap())
             .iter()
@@ -149,20 +205,22 @@ impl Model {
         for (i, poc) in pocs.iter().copied().enumerate().skip(1) {
             let prev_i = i - 1;
             for j in label.allowed_labels(poc).iter().copied() {
-                let mut best_j = 0;
-                let mut best_score = i32::MIN;
+                let mut best = None;
                 for prev_j in label.prev_labels(j).iter().copied() {
                     if prev[[prev_i, prev_j]] == usize::MAX {
                         continue;
                     }
-                    let score = scores[[prev_i, prev_j]] + self.ll_weights[[prev_j, j]];
-                    if score > best_score {
-                        best_j = prev_j;
-                        best_score = score;
+                    let score =
+                        scores[[prev_i, prev_j]].saturating_add(self.ll_weights[[prev_j, j]]);
+                    if best.is_none_or(|(_, best_score)| score > best_score) {
+                        best = Some((prev_j, score));
                     }
                 }
-                scores[[i, j]] += best_score;
-                prev[[i, j]] = best_j;
+                // labels without any reachable predecessor stay unreachable
+                if let Some((best_j, best_score)) = best {
+                    scores[[i, j]] = scores[[i, j]].saturating_add(best_score);
+                    prev[[i, j]] = best_j;
+                }
             }
         }
         let last_row = prev.nrows() - 1;
@@ -177,9 +235,33 @@ impl Model {
                 answer.push(last);
             }
             answer.reverse();
-            Some(answer)
+            Ok(answer)
         } else {
-            None
+            Err(Error::NoPath)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_unreachable_label() {
+        // `1v` has no predecessor, so `2v` must never be chosen
+        let label = Label::load(&mut "0n\n1n\n2n\n3w\n1v\n2v\n".as_bytes()).unwrap();
+        let model = Model {
+            ll_weights: Array2::from_elem((6, 6), -1),
+            fl_weights: Array2::zeros((1, 6)),
+        };
+        let pocs = [Poc::BS, Poc::ANY, Poc::ES];
+        let mut scores = Array2::zeros((3, 6));
+        let path = model.decode(&mut scores, &pocs, &label).unwrap();
+        assert!(path.iter().all(|&i| label.label(i).1 != "v"));
+        let mut scores = Array2::zeros((2, 6));
+        assert!(matches!(
+            model.decode(&mut scores, &pocs, &label),
+            Err(Error::Misaligned)
+        ));
+    }
+}
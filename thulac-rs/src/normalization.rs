@@ -0,0 +1,123 @@
+use std::{ops::Range, str::FromStr};
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How the raw text is normalized before it is fed to the model. Words
+/// returned by [`crate::Thulac::cut`] are normalized as well, while their
+/// ranges still point into the raw text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// Chars are fed to the model as they are.
+    #[default]
+    None,
+    /// NFKC per extended grapheme cluster, so `㈠` becomes `(一)`, `①`
+    /// becomes `1` and half-width katakana become full-width. Zero-width
+    /// chars and variation selectors are dropped.
+    Nfkc,
+    /// [`Normalization::Nfkc`] followed by lowercasing.
+    Casefold,
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Normalization::None),
+            "nfkc" => Ok(Normalization::Nfkc),
+            "casefold" | "nfkc_cf" => Ok(Normalization::Casefold),
+            _ => Err(format!("unknown normalization `{}`", s)),
+        }
+    }
+}
+
+/// Default ignorable code points, which are invisible and only split words
+/// apart. Zero width joiners and non-joiners are kept, as they join emoji
+/// sequences and change the shape of letters in some scripts.
+fn is_ignorable(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{AD}'
+            | '\u{34F}'
+            | '\u{61C}'
+            | '\u{115F}'..='\u{1160}'
+            | '\u{17B4}'..='\u{17B5}'
+            | '\u{180B}'..='\u{180F}'
+            | '\u{200B}'
+            | '\u{200E}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{206F}'
+            | '\u{3164}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FEFF}'
+            | '\u{FFA0}'
+            | '\u{1BCA0}'..='\u{1BCA3}'
+            | '\u{1D173}'..='\u{1D17A}'
+            | '\u{E0000}'..='\u{E0FFF}'
+    )
+}
+
+impl Normalization {
+    /// Normalizes `raw`, pushing each resulting char along with the byte
+    /// range of `raw` it comes from. Chars expanded from the same grapheme,
+    /// like `f` and `i` from `ﬁ`, share its range; dropped chars produce
+    /// nothing.
+    pub fn apply(self, raw: &str, chars: &mut Vec<(char, Range<usize>)>) {
+        if self == Normalization::None {
+            chars.extend(raw.char_indices().map(|(i, ch)| (ch, i..i + ch.len_utf8())));
+            return;
+        }
+        for (i, grapheme) in raw.grapheme_indices(true) {
+            let range = i..i + grapheme.len();
+            let normalized = grapheme.nfkc().filter(|&ch| !is_ignorable(ch));
+            if self == Normalization::Casefold {
+                chars.extend(
+                    normalized
+                        .flat_map(char::to_lowercase)
+                        .map(|ch| (ch, range.clone())),
+                );
+            } else {
+                chars.extend(normalized.map(|ch| (ch, range.clone())));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(normalization: Normalization, raw: &str) -> (String, Vec<Range<usize>>) {
+        let mut chars = Vec::new();
+        normalization.apply(raw, &mut chars);
+        chars.into_iter().unzip()
+    }
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(
+            normalize(Normalization::None, "ﬁ①"),
+            ("ﬁ①".into(), vec![0..3, 3..6])
+        );
+        assert_eq!(
+            normalize(Normalization::Nfkc, "㈠ﬁ\u{200b}ｶﾞ①e\u{301}"),
+            (
+                "(一)fiガ1é".into(),
+                vec![0..3, 0..3, 0..3, 3..6, 3..6, 9..15, 15..18, 18..21]
+            )
+        );
+        assert_eq!(
+            normalize(Normalization::Nfkc, "❤\u{fe0f}!"),
+            ("❤!".into(), vec![0..6, 6..7])
+        );
+        assert_eq!(
+            normalize(Normalization::Nfkc, "👨\u{200d}👧"),
+            ("👨\u{200d}👧".into(), vec![0..11, 0..11, 0..11])
+        );
+        assert_eq!(
+            normalize(Normalization::Casefold, "ＡＢc"),
+            ("abc".into(), vec![0..3, 3..6, 6..7])
+        );
+    }
+}
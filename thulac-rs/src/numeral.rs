@@ -0,0 +1,348 @@
+use std::ops::Range;
+
+use crate::{recognize::glued_to_ascii, PatternRecognizer, Recognizer};
+
+/// Arabic digits, half-width or full-width.
+macro_rules! d {
+    () => {
+        "[0-9０-９]"
+    };
+}
+
+/// Chinese digits and units.
+macro_rules! c {
+    () => {
+        "[零〇一二两三四五六七八九十百千万亿]"
+    };
+}
+
+const DATES: [&str; 5] = [
+    concat!(d!(), "{4}[-/.]", d!(), "{1,2}[-/.]", d!(), "{1,2}"),
+    concat!(
+        "(?:",
+        d!(),
+        "{4}年)?",
+        d!(),
+        "{1,2}月(?:",
+        d!(),
+        "{1,2}[日号])?"
+    ),
+    concat!(d!(), "{4}年"),
+    "[〇零一二三四五六七八九]{4}年(?:[一二三四五六七八九十]{1,2}月(?:[一二三四五六七八九十]{1,3}[日号])?)?",
+    "[一二三四五六七八九十]{1,2}月[一二三四五六七八九十]{1,3}[日号]",
+];
+
+const TIMES: [&str; 2] = [
+    concat!(d!(), "{1,2}[:：]", d!(), "{2}(?:[:：]", d!(), "{2})?"),
+    concat!(d!(), "{1,2}点(?:", d!(), "{1,2}分|半)?"),
+];
+
+const NUMBERS: [&str; 4] = [
+    concat!(d!(), "+(?:,", d!(), "{3})*(?:[.．]", d!(), "+)?[%％]?"),
+    "百分之[零一二两三四五六七八九十百点]+",
+    concat!(
+        "[一二两三四五六七八九十]",
+        c!(),
+        "*点[零〇一二三四五六七八九]+"
+    ),
+    concat!("[一二两三四五六七八九十]", c!(), "+"),
+];
+
+/// Recognizes dates and times, tagged `t`, and numbers, including decimals,
+/// percentages and Chinese numerals of at least two characters, tagged `m`.
+/// Expressions glued to ASCII letters or digits, as in `iPhone13`, are left
+/// to the model. See [`normalize_numeral`] for their values.
+pub struct NumeralRecognizer {
+    patterns: PatternRecognizer,
+}
+
+impl NumeralRecognizer {
+    pub fn new() -> Self {
+        let mut patterns = PatternRecognizer::new();
+        for pattern in DATES.iter().chain(TIMES.iter()) {
+            patterns.add(pattern, "t".into()).unwrap();
+        }
+        for pattern in NUMBERS {
+            patterns.add(pattern, "m".into()).unwrap();
+        }
+        Self { patterns }
+    }
+}
+
+impl Default for NumeralRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recognizer for NumeralRecognizer {
+    fn recognize<'a>(&'a self, raw: &str, spans: &mut Vec<(Range<usize>, &'a str)>) {
+        let mut found = Vec::new();
+        self.patterns.recognize(raw, &mut found);
+        spans.extend(
+            found
+                .into_iter()
+                .filter(|(range, _)| !glued_to_ascii(raw, range)),
+        );
+    }
+}
+
+/// The value of a numeric or temporal expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NumeralValue {
+    Number(f64),
+    /// A percentage, `35.0` for `35%`.
+    Percent(f64),
+    /// An ISO 8601 date, `2021-10-18`, `2021-10`, `2021` or `--10-18` when
+    /// the year is missing.
+    Date(String),
+    /// A time of day, `10:30` or `10:30:15`.
+    Time(String),
+}
+
+fn digit(ch: char) -> Option<u32> {
+    match ch {
+        '0'..='9' => ch.to_digit(10),
+        '０'..='９' => Some(ch as u32 - '０' as u32),
+        '零' | '〇' => Some(0),
+        '一' => Some(1),
+        '二' | '两' => Some(2),
+        '三' => Some(3),
+        '四' => Some(4),
+        '五' => Some(5),
+        '六' => Some(6),
+        '七' => Some(7),
+        '八' => Some(8),
+        '九' => Some(9),
+        _ => None,
+    }
+}
+
+/// Parses Arabic or Chinese integers, like `2021`, `二〇二一` or `两千零二十一`.
+/// A digit right after a unit counts in the next lower unit, so `三百五` is
+/// 350 and `一万五` is 15000. Values overflowing `u64` are `None`.
+fn parse_integer(s: &str) -> Option<u64> {
+    if s.is_empty() {
+        return None;
+    }
+    if s.chars().all(|ch| digit(ch).is_some()) {
+        return s.chars().try_fold(0u64, |acc, ch| {
+            acc.checked_mul(10)?.checked_add(digit(ch)? as u64)
+        });
+    }
+    let (mut total, mut section, mut number) = (0u64, 0u64, None);
+    // the last unit, or 1 after a zero, scaling a trailing digit
+    let mut unit = 1;
+    for ch in s.chars() {
+        if let Some(d) = digit(ch) {
+            number = Some(d as u64);
+            if d == 0 {
+                unit = 1;
+            }
+            continue;
+        }
+        match ch {
+            '十' | '百' | '千' => {
+                unit = match ch {
+                    '十' => 10,
+                    '百' => 100,
+                    _ => 1000,
+                };
+                section = section.checked_add(number.take().unwrap_or(1).checked_mul(unit)?)?;
+            }
+            '万' | '亿' => {
+                let value = section.checked_add(number.take().unwrap_or(0))?;
+                let value = if value == 0 && total == 0 { 1 } else { value };
+                if ch == '万' {
+                    unit = 10_000;
+                    total = total.checked_add(value.checked_mul(unit)?)?;
+                } else {
+                    unit = 100_000_000;
+                    total = total.checked_add(value)?.checked_mul(unit)?;
+                }
+                section = 0;
+            }
+            _ => return None,
+        }
+    }
+    let last = number.unwrap_or(0).checked_mul((unit / 10).max(1))?;
+    total.checked_add(section)?.checked_add(last)
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    let s = s
+        .chars()
+        .filter(|&ch| ch != ',')
+        .map(|ch| match ch {
+            '０'..='９' => char::from_u32(ch as u32 - '０' as u32 + '0' as u32).unwrap(),
+            '．' => '.',
+            _ => ch,
+        })
+        .collect::<String>();
+    if s.starts_with(|ch: char| ch.is_ascii_digit()) {
+        s.parse().ok()
+    } else if let Some((integer, fraction)) = s.split_once('点') {
+        let fraction = fraction
+            .chars()
+            .map(|ch| digit(ch).and_then(|d| char::from_digit(d, 10)))
+            .collect::<Option<String>>()?;
+        format!("{}.{}", parse_integer(integer)?, fraction)
+            .parse()
+            .ok()
+    } else {
+        parse_integer(&s).map(|x| x as f64)
+    }
+}
+
+fn parse_date(s: &str) -> Option<String> {
+    let mut fields = s
+        .split(['年', '月', '日', '号', '-', '/', '.'])
+        .filter(|x| !x.is_empty())
+        .map(parse_integer);
+    let (year, month, day) = if s.contains('年') || !s.contains('月') {
+        (Some(fields.next()?), fields.next(), fields.next())
+    } else {
+        (None, fields.next(), fields.next())
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    let year = year.map(|x| x.map(|x| format!("{:04}", x)));
+    let month = month.map(|x| x.filter(|x| (1..=12).contains(x)));
+    let day = day.map(|x| x.filter(|x| (1..=31).contains(x)));
+    match (year, month, day) {
+        (Some(y), Some(m), Some(d)) => Some(format!("{}-{:02}-{:02}", y?, m?, d?)),
+        (Some(y), Some(m), None) => Some(format!("{}-{:02}", y?, m?)),
+        (Some(y), None, None) => y,
+        (None, Some(m), Some(d)) => Some(format!("--{:02}-{:02}", m?, d?)),
+        (None, Some(m), None) => Some(format!("--{:02}", m?)),
+        _ => None,
+    }
+}
+
+fn parse_time(s: &str) -> Option<String> {
+    let s = s.replace('：', ":");
+    let (hour, rest) = s.split_once([':', '点'])?;
+    let hour = parse_integer(hour).filter(|&x| x <= 24)?;
+    let fields = if rest.is_empty() {
+        vec![0]
+    } else if rest == "半" {
+        vec![30]
+    } else {
+        rest.trim_end_matches('分')
+            .split(':')
+            .map(|x| parse_integer(x).filter(|&x| x < 60))
+            .collect::<Option<Vec<_>>>()?
+    };
+    Some(
+        std::iter::once(hour)
+            .chain(fields)
+            .map(|x| format!("{:02}", x))
+            .collect::<Vec<_>>()
+            .join(":"),
+    )
+}
+
+/// Normalizes a word tagged by [`NumeralRecognizer`]: `t` words to
+/// [`NumeralValue::Date`] or [`NumeralValue::Time`], and `m` words to [`NumeralValue::Number`]
+/// or [`NumeralValue::Percent`]. Returns `None` for other words or values out of
+/// range.
+pub fn normalize_numeral(word: &str, tag: &str) -> Option<NumeralValue> {
+    match tag {
+        "t" if word.contains([':', '：', '点']) => parse_time(word).map(NumeralValue::Time),
+        "t" => parse_date(word).map(NumeralValue::Date),
+        "m" => {
+            if let Some(rest) = word.strip_prefix("百分之") {
+                parse_number(rest).map(NumeralValue::Percent)
+            } else if let Some(rest) = word.strip_suffix(['%', '％']) {
+                parse_number(rest).map(NumeralValue::Percent)
+            } else {
+                parse_number(word).map(NumeralValue::Number)
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{recognize::resolve, Recognizer};
+
+    #[test]
+    fn test_numeral() {
+        let numeral = NumeralRecognizer::new();
+        let raw = "2021年10月18日10:30在3楼，涨了3.5%，约两千零二十一人，iPhone13";
+        let mut spans = Vec::new();
+        numeral.recognize(raw, &mut spans);
+        let spans = resolve(raw, spans)
+            .into_iter()
+            .map(|(range, tag)| (&raw[range], tag))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                ("2021年10月18日", "t"),
+                ("10:30", "t"),
+                ("3", "m"),
+                ("3.5%", "m"),
+                ("两千零二十一", "m"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let date = |x: &str| Some(NumeralValue::Date(x.into()));
+        let time = |x: &str| Some(NumeralValue::Time(x.into()));
+        assert_eq!(normalize_numeral("2021年10月18日", "t"), date("2021-10-18"));
+        assert_eq!(normalize_numeral("２０２１/1/8", "t"), date("2021-01-08"));
+        assert_eq!(normalize_numeral("二〇二一年十月", "t"), date("2021-10"));
+        assert_eq!(normalize_numeral("10月18号", "t"), date("--10-18"));
+        assert_eq!(normalize_numeral("13月1日", "t"), None);
+        assert_eq!(normalize_numeral("10:30", "t"), time("10:30"));
+        assert_eq!(normalize_numeral("8点半", "t"), time("08:30"));
+        assert_eq!(
+            normalize_numeral("1,234.5", "m"),
+            Some(NumeralValue::Number(1234.5))
+        );
+        assert_eq!(
+            normalize_numeral("３．５％", "m"),
+            Some(NumeralValue::Percent(3.5))
+        );
+        assert_eq!(
+            normalize_numeral("百分之三十", "m"),
+            Some(NumeralValue::Percent(30.0))
+        );
+        assert_eq!(
+            normalize_numeral("两千零二十一", "m"),
+            Some(NumeralValue::Number(2021.0))
+        );
+        assert_eq!(
+            normalize_numeral("一亿二千万", "m"),
+            Some(NumeralValue::Number(1.2e8))
+        );
+        assert_eq!(
+            normalize_numeral("十五", "m"),
+            Some(NumeralValue::Number(15.0))
+        );
+        assert_eq!(
+            normalize_numeral("二点五", "m"),
+            Some(NumeralValue::Number(2.5))
+        );
+        assert_eq!(
+            normalize_numeral("三百五", "m"),
+            Some(NumeralValue::Number(350.0))
+        );
+        assert_eq!(
+            normalize_numeral("一万五", "m"),
+            Some(NumeralValue::Number(15000.0))
+        );
+        assert_eq!(
+            normalize_numeral("三百零五", "m"),
+            Some(NumeralValue::Number(305.0))
+        );
+        assert_eq!(normalize_numeral("九千亿亿亿", "m"), None);
+        assert_eq!(normalize_numeral("好", "a"), None);
+    }
+}
@@ -0,0 +1,148 @@
+use std::{cmp::Reverse, collections::HashMap, ops::Range, sync::Arc};
+
+use crate::{model, Dat, Thulac};
+
+/// Tags of the words that are never out of vocabulary, not being dictionary
+/// words: punctuation, numbers, dates and times, and the tokens of the
+/// bundled recognizers, but emoji, which have no letters anyway and share
+/// the tag `x` of foreign words.
+const IGNORED_TAGS: [&str; 10] = [
+    "w", "m", "t", "url", "email", "mention", "hashtag", "pid", "name", "e",
+];
+
+/// The words known to the system.
+pub enum Lexicon {
+    /// A set loaded with `insert_end`, as by [`Dat::load_set_txt`], such as
+    /// the words of the training data.
+    Words(Dat),
+    /// Words whose chars and pairs of adjacent chars all have unigram and
+    /// bigram features in the [`Dat`] of a model. Chars seen together only
+    /// across words also count as known, so fewer words are out of
+    /// vocabulary than with the words of the training data.
+    Features(Arc<Dat>),
+}
+
+impl Lexicon {
+    /// The lexicon of the features of `thulac`.
+    pub fn features(thulac: &Thulac) -> Self {
+        Lexicon::Features(thulac.dat.clone())
+    }
+    pub fn contains(&self, word: &str) -> bool {
+        match self {
+            Lexicon::Words(dat) => dat.contains(word),
+            Lexicon::Features(dat) => model::has_features(dat, word),
+        }
+    }
+}
+
+/// Marks the words returned by [`Thulac::cut`] as in or out of vocabulary,
+/// and counts the out-of-vocabulary words of a corpus for dictionary
+/// curation.
+pub struct OovAnalyzer {
+    lexicon: Lexicon,
+    /// Number of words marked by [`OovAnalyzer::add`], without the ignored
+    /// ones.
+    words: usize,
+    counts: HashMap<String, usize>,
+}
+
+impl OovAnalyzer {
+    pub fn new(lexicon: Lexicon) -> Self {
+        Self {
+            lexicon,
+            words: 0,
+            counts: HashMap::new(),
+        }
+    }
+    /// Whether a word is a dictionary word: not whitespace, punctuation,
+    /// a number or a recognized token.
+    fn is_dictionary_word<S: AsRef<str>>(word: &S, tag: &str) -> bool {
+        word.as_ref().chars().any(char::is_alphabetic) && !IGNORED_TAGS.contains(&tag)
+    }
+    /// Whether each of `words` is out of vocabulary. Words that are not
+    /// dictionary words, such as punctuation, numbers and whitespace, never
+    /// are.
+    pub fn mark<S: AsRef<str>>(&self, words: &[(Range<usize>, S, &str)]) -> Vec<bool> {
+        words
+            .iter()
+            .map(|(_, word, tag)| {
+                Self::is_dictionary_word(word, tag) && !self.lexicon.contains(word.as_ref())
+            })
+            .collect()
+    }
+    /// Marks words as [`OovAnalyzer::mark`] does, counting them.
+    pub fn add<S: AsRef<str>>(&mut self, words: &[(Range<usize>, S, &str)]) -> Vec<bool> {
+        let oov = self.mark(words);
+        for ((_, word, tag), &oov) in words.iter().zip(oov.iter()) {
+            if !Self::is_dictionary_word(word, tag) {
+                continue;
+            }
+            self.words += 1;
+            if oov {
+                *self.counts.entry(word.as_ref().to_owned()).or_default() += 1;
+            }
+        }
+        oov
+    }
+    /// Number of dictionary words counted.
+    pub fn words(&self) -> usize {
+        self.words
+    }
+    /// Number of out-of-vocabulary words counted, including repetitions.
+    pub fn oov_words(&self) -> usize {
+        self.counts.values().sum()
+    }
+    /// Number of distinct out-of-vocabulary words.
+    pub fn distinct_oov_words(&self) -> usize {
+        self.counts.len()
+    }
+    /// The `top` most frequent out-of-vocabulary words with their counts, by
+    /// decreasing count, then by word.
+    pub fn top(&self, top: usize) -> Vec<(&str, usize)> {
+        let mut counts = self
+            .counts
+            .iter()
+            .map(|(word, &count)| (&word[..], count))
+            .collect::<Vec<_>>();
+        counts.sort_unstable_by_key(|&(word, count)| (Reverse(count), word));
+        counts.truncate(top);
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oov() {
+        let words = "我们_r 喜欢_v 绝绝子_a ，_w 3_m 个_q 绝绝子_a abc_x";
+        let (_, words) = crate::tests::gold(words);
+        let lexicon = Dat::load_set_txt(&mut "我们\n喜欢\n个\n".as_bytes(), true).unwrap();
+        let mut analyzer = OovAnalyzer::new(Lexicon::Words(lexicon));
+        assert_eq!(
+            analyzer.add(&words),
+            [false, false, true, false, false, false, true, true]
+        );
+        assert_eq!(analyzer.words(), 6);
+        assert_eq!(analyzer.oov_words(), 3);
+        assert_eq!(analyzer.top(1), [("绝绝子", 2)]);
+        // features of the chars of `我们` and `喜`, and of the pair `我们`
+        let features = Dat::build(vec![("我 1", 0), ("们 1", 1), ("我们 2", 2), ("喜 1", 3)]);
+        let analyzer = OovAnalyzer::new(Lexicon::Features(Arc::new(features)));
+        assert_eq!(analyzer.mark(&words[..2]), [false, true]);
+    }
+
+    #[test]
+    fn test_oov_recognized() {
+        let words = "看_v @Alice_mention 的_u #清华#_hashtag (´･ω･`)_e 回复_v #123456_pid";
+        let (_, words) = crate::tests::gold(words);
+        let lexicon = Dat::load_set_txt(&mut "看\n的\n".as_bytes(), true).unwrap();
+        let mut analyzer = OovAnalyzer::new(Lexicon::Words(lexicon));
+        assert_eq!(
+            analyzer.add(&words),
+            [false, false, false, false, false, true, false]
+        );
+        assert_eq!(analyzer.top(10), [("回复", 1)]);
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    io::{Result, Write},
+    ops::Range,
+    str::FromStr,
+};
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `word_tag` pairs separated by spaces, as printed by the original THULAC.
+    Thulac,
+    /// Words separated by spaces.
+    Seg,
+    /// One JSON array of tokens with byte offsets per line.
+    Json,
+    /// One token per line in CoNLL-U, sentences separated by blank lines.
+    Conll,
+    /// One `word\ttag\tstart\tend` line per token, sentences separated by
+    /// blank lines.
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "thulac" => Ok(Format::Thulac),
+            "seg" => Ok(Format::Seg),
+            "json" | "jsonl" => Ok(Format::Json),
+            "conll" | "conllu" => Ok(Format::Conll),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!("unknown output format `{}`", s)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Token<'a> {
+    word: &'a str,
+    tag: &'a str,
+    start: usize,
+    end: usize,
+}
+
+/// Writes the result of [`crate::Thulac::cut`] for one line of input. Words
+/// without text, i.e. the whitespace between words, are skipped.
+pub struct Formatter {
+    format: Format,
+    separator: String,
+}
+
+impl Formatter {
+    /// `separator` goes between word and tag in [`Format::Thulac`].
+    pub fn new(format: Format, separator: String) -> Self {
+        Self { format, separator }
+    }
+    pub fn write<W: Write, S: AsRef<str>>(
+        &self,
+        writer: &mut W,
+        raw: &str,
+        words: &[(Range<usize>, S, &str)],
+    ) -> Result<()> {
+        let words = words
+            .iter()
+            .map(|(range, word, tag)| (range, word.as_ref(), *tag))
+            .filter(|(_, word, _)| !word.is_empty());
+        match self.format {
+            Format::Thulac => {
+                for (i, (_, word, tag)) in words.enumerate() {
+                    if i != 0 {
+                        writer.write_all(b" ")?;
+                    }
+                    write!(writer, "{}{}{}", word, self.separator, tag)?;
+                }
+                writeln!(writer)
+            }
+            Format::Seg => {
+                for (i, (_, word, _)) in words.enumerate() {
+                    if i != 0 {
+                        writer.write_all(b" ")?;
+                    }
+                    writer.write_all(word.as_bytes())?;
+                }
+                writeln!(writer)
+            }
+            Format::Json => {
+                let tokens = words
+                    .map(|(range, word, tag)| Token {
+                        word,
+                        tag,
+                        start: range.start,
+                        end: range.end,
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::to_writer(&mut *writer, &tokens)?;
+                writeln!(writer)
+            }
+            Format::Conll => {
+                // CoNLL-U sentences have at least one token
+                let mut words = words.peekable();
+                if words.peek().is_none() {
+                    return Ok(());
+                }
+                writeln!(writer, "# text = {}", raw.trim_end())?;
+                for (i, (range, word, tag)) in words.enumerate() {
+                    writeln!(
+                        writer,
+                        "{}\t{}\t_\t_\t{}\t_\t_\t_\t_\tTokenRange={}:{}",
+                        i + 1,
+                        word,
+                        tag,
+                        range.start,
+                        range.end
+                    )?;
+                }
+                writeln!(writer)
+            }
+            Format::Tsv => {
+                for (range, word, tag) in words {
+                    writeln!(writer, "{}\t{}\t{}\t{}", word, tag, range.start, range.end)?;
+                }
+                writeln!(writer)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(format: Format) -> String {
+        let words = [(0..6, "你好", "v"), (6..7, "", "w"), (7..10, "！", "w")];
+        let mut buf = Vec::new();
+        Formatter::new(format, "_".into())
+            .write(&mut buf, "你好 ！", &words)
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(format(Format::Thulac), "你好_v ！_w\n");
+        assert_eq!(format(Format::Seg), "你好 ！\n");
+        assert_eq!(
+            format(Format::Json),
+            "[{\"word\":\"你好\",\"tag\":\"v\",\"start\":0,\"end\":6},\
+             {\"word\":\"！\",\"tag\":\"w\",\"start\":7,\"end\":10}]\n"
+        );
+        assert_eq!(
+            format(Format::Conll),
+            "# text = 你好 ！\n1\t你好\t_\t_\tv\t_\t_\t_\t_\tTokenRange=0:6\n\
+             2\t！\t_\t_\tw\t_\t_\t_\t_\tTokenRange=7:10\n\n"
+        );
+        assert_eq!(format(Format::Tsv), "你好\tv\t0\t6\n！\tw\t7\t10\n\n");
+        let mut buf = Vec::new();
+        Formatter::new(Format::Conll, "_".into())
+            .write(&mut buf, " ", &[(0..1, "", "w")])
+            .unwrap();
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io::{BufRead, Error, Result},
+    ops::Range,
+};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{Dat, Term};
+
+/// The pinyin of a word, for search by pinyin or by initials.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    /// Toneless syllables run together, like `qinghua`.
+    pub full: String,
+    /// First letters of the syllables, like `qh`.
+    pub initials: String,
+}
+
+/// A pinyin dictionary of chars and words. Polyphones are disambiguated by
+/// the longest word of the dictionary found in a segmented word, so `行` in
+/// `银行` reads `hang`, and by the first reading listed otherwise.
+pub struct Pinyin {
+    /// Entries followed by `\0`, whose values index `readings`.
+    dat: Dat,
+    /// One syllable per char of each entry.
+    readings: Vec<Vec<String>>,
+}
+
+/// Strips tone marks and tone numbers, writing `ü` as `v`.
+fn plain(syllable: &str) -> String {
+    let mut result = String::new();
+    for ch in syllable.nfd() {
+        match ch {
+            '\u{308}' if result.ends_with('u') => {
+                result.pop();
+                result.push('v');
+            }
+            '\u{300}'..='\u{36F}' => (),
+            ch if ch.is_ascii_digit() => (),
+            ch => result.push(ch.to_ascii_lowercase()),
+        }
+    }
+    result
+}
+
+impl Pinyin {
+    /// Loads lines of `word\tsyllables`, one syllable per char separated by
+    /// spaces, like `银行\tyín háng` or `重庆\tchong2 qing4`. A char with
+    /// several readings is listed on several lines, the most common first.
+    /// Tones are dropped. Empty lines are skipped, and an empty file gives an
+    /// empty dictionary.
+    pub fn load_txt<R: BufRead>(reader: &mut R) -> Result<Self> {
+        let mut keys = HashMap::new();
+        let mut readings = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let (word, syllables) = line
+                .split_once('\t')
+                .ok_or_else(|| Error::other("missing delimiter"))?;
+            let syllables = syllables.split_whitespace().map(plain).collect::<Vec<_>>();
+            if syllables.len() != word.chars().count() {
+                return Err(Error::other("syllable count differs from char count"));
+            }
+            if let Entry::Vacant(entry) = keys.entry(word.to_owned() + "\0") {
+                entry.insert(readings.len() as i32);
+                readings.push(syllables);
+            }
+        }
+        let dat = Dat::build(keys.iter().map(|(x, &v)| (&x[..], v)).collect());
+        Ok(Self { dat, readings })
+    }
+    /// The syllable of each char of `word`, or `None` for chars without one.
+    pub fn syllables<'a>(&'a self, word: &str) -> Vec<Option<&'a str>> {
+        let chars = word.chars().collect::<Vec<_>>();
+        let mut result = Vec::with_capacity(chars.len());
+        while result.len() < chars.len() {
+            let start = result.len();
+            let mut node = self.dat.root();
+            let mut best = None;
+            for &ch in chars[start..].iter() {
+                node = match self.dat.child(node, ch) {
+                    Some(x) => x,
+                    None => break,
+                };
+                if let Some(leaf) = self.dat.child(node, '\0') {
+                    best = Some(self.dat.base(leaf) as usize);
+                }
+            }
+            match best {
+                Some(x) => result.extend(self.readings[x].iter().map(|x| Some(&x[..]))),
+                None => result.push(None),
+            }
+        }
+        result
+    }
+    /// The pinyin of `word`, keeping chars without pinyin, such as letters,
+    /// as they are. `None` if no char has pinyin.
+    pub fn annotate(&self, word: &str) -> Option<Annotation> {
+        let syllables = self.syllables(word);
+        if syllables.iter().all(Option::is_none) {
+            return None;
+        }
+        let mut full = String::new();
+        let mut initials = String::new();
+        for (ch, syllable) in word.chars().zip(syllables) {
+            match syllable {
+                Some(syllable) => {
+                    full.push_str(syllable);
+                    initials.extend(syllable.chars().next());
+                }
+                None => {
+                    full.extend(ch.to_lowercase());
+                    initials.extend(ch.to_lowercase());
+                }
+            }
+        }
+        Some(Annotation { full, initials })
+    }
+    /// Index terms for the pinyin of `word`, found at `range` of the raw
+    /// text: the full pinyin, then the initials for words of several chars.
+    pub fn terms(&self, range: Range<usize>, word: &str) -> Vec<Term> {
+        let Some(Annotation { full, initials }) = self.annotate(word) else {
+            return Vec::new();
+        };
+        let mut terms = vec![Term {
+            range: range.clone(),
+            text: full,
+        }];
+        if word.chars().nth(1).is_some() {
+            terms.push(Term {
+                range,
+                text: initials,
+            });
+        }
+        terms
+    }
+    /// The pinyin terms of each word returned by [`crate::Thulac::cut`], to
+    /// be indexed along with the words themselves.
+    pub fn index<S: AsRef<str>>(&self, words: &[(Range<usize>, S, &str)]) -> Vec<Term> {
+        words
+            .iter()
+            .filter(|(_, word, _)| !word.as_ref().is_empty())
+            .flat_map(|(range, word, _)| self.terms(range.clone(), word.as_ref()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinyin() {
+        let dict = "清\tqīng\n华\thuá\n华\thuà\n行\txíng\n行\tháng\n银\tyin2\n\
+                    银行\tyín háng\n绿\tlǜ\n";
+        let pinyin = Pinyin::load_txt(&mut dict.as_bytes()).unwrap();
+        assert_eq!(pinyin.syllables("银行"), [Some("yin"), Some("hang")]);
+        assert_eq!(pinyin.syllables("行"), [Some("xing")]);
+        assert_eq!(pinyin.syllables("绿x"), [Some("lv"), None]);
+        assert_eq!(
+            pinyin.annotate("清华"),
+            Some(Annotation {
+                full: "qinghua".into(),
+                initials: "qh".into()
+            })
+        );
+        assert_eq!(pinyin.annotate("abc"), None);
+        let words = [(0..6, "清华", "ni"), (6..7, "", "w"), (7..10, "行", "v")];
+        let terms = pinyin
+            .index(&words)
+            .into_iter()
+            .map(|x| x.text)
+            .collect::<Vec<_>>();
+        assert_eq!(terms, ["qinghua", "qh", "xing"]);
+        assert!(Pinyin::load_txt(&mut "银行\tyin".as_bytes()).is_err());
+        let empty = Pinyin::load_txt(&mut "".as_bytes()).unwrap();
+        assert_eq!(empty.annotate("清华"), None);
+    }
+}
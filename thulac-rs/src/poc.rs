@@ -1,7 +1,10 @@
+use std::{collections::HashMap, io::BufRead, ops::Range};
+
 use bitflags::bitflags;
-use ctor::ctor;
 use phf::{phf_set, Set};
 
+use crate::{Error, Result};
+
 bitflags! {
     pub struct Poc: u8 {
         const B = 0x1;
@@ -25,41 +28,154 @@ pub static MULTI_PUNC: Set<char> = phf_set! {
     '[', ']', '\\', '$', '^', '_', '`', '~',
 };
 
-#[ctor]
-fn check_disjoint() {
-    assert!(SINGLE_PUNC.is_disjoint(&MULTI_PUNC));
-}
-
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CharKind {
+    /// Dropped from the model input; always ends a word.
     Space,
+    /// Always a word by itself.
     SinglePunc,
+    /// Joined with adjacent characters of the same kind, as `abc` or `——`.
     MultiPunc,
     Others,
 }
 
 impl CharKind {
-    pub fn find(ch: char) -> Self {
-        if ch.is_whitespace() {
-            CharKind::Space
-        } else if SINGLE_PUNC.contains(&ch) {
-            CharKind::SinglePunc
-        } else if ch.is_ascii_alphanumeric() || MULTI_PUNC.contains(&ch) {
-            CharKind::MultiPunc
-        } else {
-            CharKind::Others
+    fn name(self) -> &'static str {
+        match self {
+            CharKind::Space => "space",
+            CharKind::SinglePunc => "single",
+            CharKind::MultiPunc => "multi",
+            CharKind::Others => "others",
+        }
+    }
+}
+
+/// Classifies characters for [`Poc::build`]. The default classes
+/// [`SINGLE_PUNC`] as [`CharKind::SinglePunc`], and ASCII letters, digits and
+/// [`MULTI_PUNC`] as [`CharKind::MultiPunc`]. Characters not listed are
+/// [`CharKind::Space`] if they are whitespace and [`CharKind::Others`]
+/// otherwise.
+#[derive(Clone, Debug)]
+pub struct CharClassifier {
+    kinds: HashMap<char, CharKind>,
+}
+
+impl Default for CharClassifier {
+    fn default() -> Self {
+        let mut kinds = HashMap::new();
+        for ch in ('0'..='9').chain('A'..='Z').chain('a'..='z') {
+            kinds.insert(ch, CharKind::MultiPunc);
         }
+        for &ch in MULTI_PUNC.iter() {
+            kinds.insert(ch, CharKind::MultiPunc);
+        }
+        for &ch in SINGLE_PUNC.iter() {
+            kinds.insert(ch, CharKind::SinglePunc);
+        }
+        Self { kinds }
     }
 }
 
+impl CharClassifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn find(&self, ch: char) -> CharKind {
+        match self.kinds.get(&ch) {
+            Some(&kind) => kind,
+            None if ch.is_whitespace() => CharKind::Space,
+            None => CharKind::Others,
+        }
+    }
+    pub fn is_space(&self, ch: char) -> bool {
+        self.find(ch) == CharKind::Space
+    }
+    pub fn set(&mut self, ch: char, kind: CharKind) {
+        self.kinds.insert(ch, kind);
+    }
+    /// Loads the default classes, then overrides them with lines of
+    /// `class chars`, where `class` is `space`, `single`, `multi` or `others`
+    /// and `chars` are the characters to move to it, without separators.
+    /// Characters may be escaped as `\u{3000}`. Empty lines and lines
+    /// starting with `#` are skipped. A character listed in two classes is
+    /// an error.
+    pub fn load_txt<R: BufRead>(reader: &mut R) -> Result<Self> {
+        let mut classifier = Self::default();
+        let mut listed = HashMap::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason| Error::InvalidCharClass {
+                line: i + 1,
+                reason,
+            };
+            let (class, chars) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| invalid("missing characters".into()))?;
+            let kind = [
+                CharKind::Space,
+                CharKind::SinglePunc,
+                CharKind::MultiPunc,
+                CharKind::Others,
+            ]
+            .into_iter()
+            .find(|x| x.name() == class)
+            .ok_or_else(|| invalid(format!("unknown class `{}`", class)))?;
+            for ch in unescape(chars.trim()).ok_or_else(|| invalid("invalid escape".into()))? {
+                match listed.insert(ch, kind) {
+                    Some(other) if other != kind => {
+                        return Err(invalid(format!(
+                            "{:?} is both {} and {}",
+                            ch,
+                            other.name(),
+                            kind.name()
+                        )));
+                    }
+                    _ => classifier.set(ch, kind),
+                }
+            }
+        }
+        Ok(classifier)
+    }
+}
+
+/// Parses characters with `\u{XXXX}` and `\\` escapes.
+fn unescape(s: &str) -> Option<Vec<char>> {
+    let mut result = Vec::new();
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => result.push('\\'),
+            'u' => {
+                let rest = chars.as_str().strip_prefix('{')?;
+                let (hex, rest) = rest.split_once('}')?;
+                result.push(char::from_u32(u32::from_str_radix(hex, 16).ok()?)?);
+                chars = rest.chars();
+            }
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
 impl Poc {
-    pub fn build<S: IntoIterator<Item = char>>(sentence: S) -> (String, Vec<Poc>) {
+    pub fn build<S: IntoIterator<Item = char>>(
+        sentence: S,
+        classifier: &CharClassifier,
+    ) -> (String, Vec<Poc>) {
         let mut curr = CharKind::Space;
         let mut result = sentence.into_iter().fold(
             (String::new(), Vec::<Poc>::new()),
             move |(mut result, mut pocs), ch| {
                 let prev = curr;
-                curr = CharKind::find(ch);
+                curr = classifier.find(ch);
                 let last = pocs.last_mut().into_iter();
                 match (prev, curr) {
                     (CharKind::MultiPunc, CharKind::MultiPunc) => {
@@ -99,14 +215,60 @@ impl Poc {
             || x == Poc::ANY));
         (result.0, result.1)
     }
+    /// Forces the characters in `range` to form a single word, relaxing the
+    /// constraints of its neighbours so that they end and begin words.
+    pub fn force_word(pocs: &mut [Poc], range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        if range.len() == 1 {
+            pocs[range.start] = Poc::S;
+        } else {
+            pocs[range.start] = Poc::B;
+            pocs[range.start + 1..range.end - 1].fill(Poc::M);
+            pocs[range.end - 1] = Poc::E;
+        }
+        if let Some(prev) = range.start.checked_sub(1).map(|i| &mut pocs[i]) {
+            let mut poc = Poc::empty();
+            poc.set(Poc::S, prev.intersects(Poc::BS));
+            poc.set(Poc::E, prev.intersects(Poc::M | Poc::E));
+            *prev = poc;
+        }
+        if let Some(next) = pocs.get_mut(range.end) {
+            let mut poc = Poc::empty();
+            poc.set(Poc::B, next.intersects(Poc::B | Poc::M));
+            poc.set(Poc::S, next.intersects(Poc::ES));
+            *next = poc;
+        }
+    }
+    /// Forbids word boundaries inside `range`, whose chars all come from a
+    /// single grapheme of the raw text, like `fi` from `ﬁ`. Falls back to
+    /// [`Poc::force_word`] if that conflicts with the constraints already
+    /// set.
+    pub fn join(pocs: &mut [Poc], range: Range<usize>) {
+        if range.len() < 2 {
+            return;
+        }
+        let mut joined = pocs[range.clone()].to_vec();
+        let last = joined.len() - 1;
+        joined[0] &= Poc::B | Poc::M;
+        joined[1..last].iter_mut().for_each(|x| *x &= Poc::M);
+        joined[last] &= Poc::M | Poc::E;
+        if joined.iter().any(|x| x.is_empty()) {
+            Poc::force_word(pocs, range);
+        } else {
+            pocs[range].copy_from_slice(&joined);
+        }
+    }
 }
 
 pub fn punc_adjust<'a, 'b>(mut words: Vec<(&'a str, &'b str)>) -> Vec<(&'a str, &'b str)> {
     words.iter_mut().for_each(|word| {
         let mut chars = word.0.chars();
-        let ch = chars.next().unwrap();
-        if chars.next().is_none() && SINGLE_PUNC.contains(&ch) {
-            word.1 = "w";
+        if let (Some(ch), None) = (chars.next(), chars.next()) {
+            if SINGLE_PUNC.contains(&ch) {
+                word.1 = "w";
+            }
         }
     });
     words
@@ -118,13 +280,15 @@ mod tests {
 
     #[test]
     fn test_poc() {
-        assert_eq!(Poc::build("".chars()), ("".to_owned(), vec![]));
-        assert_eq!(Poc::build(" ".chars()), ("".to_owned(), vec![]));
-        assert_eq!(Poc::build(".".chars()), (".".to_owned(), vec![Poc::S]));
-        assert_eq!(Poc::build("h".chars()), ("h".to_owned(), vec![Poc::S]));
-        assert_eq!(Poc::build("我".chars()), ("我".to_owned(), vec![Poc::S]));
+        let chars = CharClassifier::default();
+        let build = |s: &str| Poc::build(s.chars(), &chars);
+        assert_eq!(build(""), ("".to_owned(), vec![]));
+        assert_eq!(build(" "), ("".to_owned(), vec![]));
+        assert_eq!(build("."), (".".to_owned(), vec![Poc::S]));
+        assert_eq!(build("h"), ("h".to_owned(), vec![Poc::S]));
+        assert_eq!(build("我"), ("我".to_owned(), vec![Poc::S]));
         assert_eq!(
-            Poc::build("hey, 你好呀！".chars()),
+            build("hey, 你好呀！"),
             (
                 "hey,你好呀！".to_owned(),
                 vec![
@@ -140,4 +304,48 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_force_word() {
+        let (_, mut pocs) = Poc::build("abc,你好呀".chars(), &CharClassifier::default());
+        Poc::force_word(&mut pocs, 1..2);
+        Poc::force_word(&mut pocs, 5..7);
+        assert_eq!(
+            pocs,
+            [Poc::S, Poc::S, Poc::S, Poc::S, Poc::S, Poc::B, Poc::E]
+        );
+    }
+
+    #[test]
+    fn test_join() {
+        let chars = CharClassifier::default();
+        let (_, mut pocs) = Poc::build("fine".chars(), &chars);
+        Poc::join(&mut pocs, 0..2);
+        assert_eq!(pocs, [Poc::B, Poc::M, Poc::M, Poc::E]);
+        let (_, mut pocs) = Poc::build("(一)".chars(), &chars);
+        Poc::join(&mut pocs, 0..3);
+        assert_eq!(pocs, [Poc::B, Poc::M, Poc::E]);
+    }
+
+    #[test]
+    fn test_char_classifier() {
+        assert!(SINGLE_PUNC.is_disjoint(&MULTI_PUNC));
+        let config = "# deployment\nsingle 「」『』~\nspace \\u{200b}\n\nothers a";
+        let chars = CharClassifier::load_txt(&mut config.as_bytes()).unwrap();
+        assert_eq!(chars.find('「'), CharKind::SinglePunc);
+        assert_eq!(chars.find('~'), CharKind::SinglePunc);
+        assert_eq!(chars.find('\u{200b}'), CharKind::Space);
+        assert_eq!(chars.find('a'), CharKind::Others);
+        assert_eq!(chars.find('—'), CharKind::MultiPunc);
+        assert_eq!(
+            Poc::build("a~~b\u{200b}c".chars(), &chars).0,
+            "a~~bc".to_owned()
+        );
+        for config in ["single ~\nmulti ~", "round ()", "single", "space \\u{zz}"] {
+            assert!(matches!(
+                CharClassifier::load_txt(&mut config.as_bytes()),
+                Err(Error::InvalidCharClass { .. })
+            ));
+        }
+    }
 }
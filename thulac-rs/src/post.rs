@@ -1,11 +1,30 @@
 use core::slice;
-use std::ops::Range;
+use std::{
+    borrow::Borrow,
+    cmp::Reverse,
+    io::{BufRead, Error, Result},
+    ops::Range,
+};
 
 use crate::Dat;
 
+/// Identifies a post-processor added to a [`crate::Thulac`]. Unlike its
+/// position, it stays the same when other post-processors are removed, and
+/// in clones of the `Thulac`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PostProcessorId(pub(crate) u64);
+
+/// Merges consecutive words forming an entry of a dictionary into one word
+/// tagged by the dictionary. When the entries of several post-processors
+/// overlap, the one with the highest priority wins, then the longest one,
+/// then the leftmost one.
 pub struct PostProcessor {
     dat: Dat,
     tag: String,
+    priority: i32,
+    /// Tag and priority of each entry, indexed by its value in `dat`, for
+    /// dictionaries loaded with [`PostProcessor::load_txt`].
+    entries: Vec<(String, i32)>,
 }
 
 unsafe fn concat_slice<'a, T>(a: &'a [T], b: &'a [T]) -> &'a [T] {
@@ -13,53 +32,206 @@ unsafe fn concat_slice<'a, T>(a: &'a [T], b: &'a [T]) -> &'a [T] {
     slice::from_raw_parts(a.as_ptr(), a.len() + b.len())
 }
 
-unsafe fn concat_str<'a>(a: &'a str, b: &'a str) -> &'a str {
+pub(crate) unsafe fn concat_str<'a>(a: &'a str, b: &'a str) -> &'a str {
     std::str::from_utf8_unchecked(concat_slice(a.as_bytes(), b.as_bytes()))
 }
 
+/// A match of a post-processor: words `start..end`, with a tag and priority.
+struct Candidate<'b> {
+    start: usize,
+    end: usize,
+    tag: &'b str,
+    priority: i32,
+}
+
 impl PostProcessor {
+    /// Every entry of `dat`, loaded with `insert_end`, gets `tag` and
+    /// priority 0.
     pub fn new(dat: Dat, tag: String) -> Self {
-        Self { dat, tag }
+        Self {
+            dat,
+            tag,
+            priority: 0,
+            entries: Vec::new(),
+        }
+    }
+    /// Sets the priority of the entries without one of their own.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+    /// Loads lines of `word`, `word\ttag` or `word\ttag\tpriority`. Entries
+    /// without a tag get `tag`, and entries without a priority get 0. Empty
+    /// lines are skipped, and only the first line of a word is used. An empty
+    /// file gives a post-processor without entries.
+    pub fn load_txt<R: BufRead>(reader: &mut R, tag: String) -> Result<Self> {
+        let mut words = Vec::new();
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split('\t');
+            let word = fields.next().unwrap();
+            let entry_tag = fields.next().filter(|x| !x.is_empty()).unwrap_or(&tag);
+            let priority = fields
+                .next()
+                .map(|x| x.parse::<i32>())
+                .transpose()
+                .map_err(|_| Error::other("invalid priority of entry"))?
+                .unwrap_or(0);
+            if word.is_empty() || fields.next().is_some() {
+                return Err(Error::other("invalid entry"));
+            }
+            words.push(word.to_owned() + "\0");
+            entries.push((entry_tag.to_owned(), priority));
+        }
+        // keep the first line of each word
+        let mut indices = (0..words.len()).collect::<Vec<_>>();
+        indices.sort_by_key(|&i| &words[i]);
+        indices.dedup_by_key(|&mut i| &words[i]);
+        let dat = Dat::build(indices.iter().map(|&i| (&words[i][..], i as i32)).collect());
+        Ok(Self {
+            dat,
+            tag,
+            priority: 0,
+            entries,
+        })
+    }
+    /// Whether `word` is an entry, as a single word.
+    pub fn contains(&self, word: &str) -> bool {
+        self.dat.contains(word)
+    }
+    pub(crate) fn dat(&self) -> &Dat {
+        &self.dat
+    }
+    /// The tag and priority of the entry whose leaf in `dat` is `leaf`.
+    pub(crate) fn entry(&self, leaf: i32) -> (&str, i32) {
+        if self.entries.is_empty() {
+            (&self.tag, self.priority)
+        } else {
+            let (tag, priority) = &self.entries[self.dat.base(leaf) as usize];
+            (tag, *priority)
+        }
+    }
+    /// The longest entry formed by `words` from `start`, as its end and leaf.
+    fn longest(&self, words: &[(Range<usize>, &str, &str)], start: usize) -> Option<(usize, i32)> {
+        let mut node = self.dat.root();
+        let mut best = None;
+        for (i, (_, word, _)) in words.iter().enumerate().skip(start) {
+            if word.is_empty() {
+                break;
+            }
+            node = match self.dat.descendant(node, word) {
+                Some(x) => x,
+                None => break,
+            };
+            if let Some(leaf) = self.dat.child(node, '\0') {
+                best = Some((i + 1, leaf));
+            }
+        }
+        best
     }
     pub fn adjust<'a, 'b>(
         &'b self,
-        mut words: Vec<(Range<usize>, &'a str, &'b str)>,
+        words: Vec<(Range<usize>, &'a str, &'b str)>,
     ) -> Vec<(Range<usize>, &'a str, &'b str)> {
-        words.reverse();
-        let mut result = Vec::new();
-        while let Some((range, word, tag)) = words.pop() {
-            if !word.is_empty() {
-                if let Some(mut pointer) = self.dat.descendant(self.dat.root(), word) {
-                    let mut best = self.dat.child(pointer, '\0').map(|_| words.len());
-                    for (index, (_, word, _)) in words.iter().enumerate().rev() {
-                        if word.is_empty() {
-                            break;
-                        }
-                        match self.dat.descendant(pointer, word) {
-                            Some(new_pointer) => {
-                                pointer = new_pointer;
-                                best = self.dat.child(pointer, '\0').map(|_| index).or(best);
-                            }
-                            None => break,
-                        }
-                    }
-                    if let Some(best) = best {
-                        let (range, word) = words.drain(best..words.len()).rev().fold(
-                            (range, word),
-                            |(acc_range, acc_word), (range, word, _)| {
-                                assert!(acc_range.end == range.start);
-                                (acc_range.start..range.end, unsafe {
-                                    concat_str(acc_word, word)
-                                })
-                            },
-                        );
-                        result.push((range, word, &self.tag[..]));
-                        continue;
-                    }
-                }
+        adjust_all(std::slice::from_ref(self), words)
+    }
+}
+
+/// Applies all of `posts` at once, resolving overlapping entries by
+/// priority rather than by the order of `posts`.
+pub(crate) fn adjust_all<'a, 'b, P: Borrow<PostProcessor>>(
+    posts: &'b [P],
+    words: Vec<(Range<usize>, &'a str, &'b str)>,
+) -> Vec<(Range<usize>, &'a str, &'b str)> {
+    let mut candidates = Vec::new();
+    for start in 0..words.len() {
+        for post in posts.iter().map(Borrow::borrow) {
+            if let Some((end, leaf)) = post.longest(&words, start) {
+                let (tag, priority) = post.entry(leaf);
+                candidates.push(Candidate {
+                    start,
+                    end,
+                    tag,
+                    priority,
+                });
+            }
+        }
+    }
+    // a stable sort keeps the earlier post-processor first among equals
+    candidates.sort_by_key(|x| (Reverse(x.priority), Reverse(x.end - x.start), x.start));
+    let mut taken = vec![false; words.len()];
+    let mut chosen = vec![None; words.len()];
+    for candidate in candidates {
+        if taken[candidate.start..candidate.end].iter().any(|&x| x) {
+            continue;
+        }
+        taken[candidate.start..candidate.end].fill(true);
+        chosen[candidate.start] = Some((candidate.end, candidate.tag));
+    }
+    let mut result = Vec::with_capacity(words.len());
+    let mut words = words.into_iter().enumerate();
+    while let Some((start, (range, word, tag))) = words.next() {
+        match chosen[start] {
+            Some((end, tag)) => {
+                let (range, word) = words.by_ref().take(end - start - 1).fold(
+                    (range, word),
+                    |(acc_range, acc_word), (_, (range, word, _))| {
+                        assert!(acc_range.end == range.start);
+                        (acc_range.start..range.end, unsafe {
+                            concat_str(acc_word, word)
+                        })
+                    },
+                );
+                result.push((range, word, tag));
             }
-            result.push((range, word, tag));
+            None => result.push((range, word, tag)),
         }
-        result
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priorities() {
+        let input = "清华大学紫荆公寓";
+        let words = [(0, 3), (3, 6), (6, 12), (12, 18), (18, 24)]
+            .map(|(start, end)| (start..end, &input[start..end], "x"))
+            .to_vec();
+        let dorms =
+            PostProcessor::load_txt(&mut "紫荆公寓\tns\t5\n".as_bytes(), "uw".into()).unwrap();
+        let schools = PostProcessor::load_txt(
+            &mut "清华大学\tni\t1\n大学\n大学紫荆\tnz\t3\n".as_bytes(),
+            "uw".into(),
+        )
+        .unwrap();
+        let posts = [schools, dorms];
+        let result = adjust_all(&posts, words.clone())
+            .into_iter()
+            .map(|(_, word, tag)| (word, tag))
+            .collect::<Vec<_>>();
+        assert_eq!(result, [("清华大学", "ni"), ("紫荆公寓", "ns")]);
+        // an emptied dictionary is still a dictionary, without entries
+        let empty = PostProcessor::load_txt(&mut "\n".as_bytes(), "uw".into()).unwrap();
+        assert!(!empty.contains("清华大学"));
+        assert_eq!(adjust_all(&[empty], words.clone()), words);
+        let idioms =
+            PostProcessor::new(Dat::build(vec![("华大学\0", 0)]), "i".into()).with_priority(9);
+        let result = idioms
+            .adjust(words)
+            .into_iter()
+            .map(|(_, word, tag)| (word, tag))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            result,
+            [("清", "x"), ("华大学", "i"), ("紫荆", "x"), ("公寓", "x")]
+        );
+        assert!(PostProcessor::load_txt(&mut "词\tn\tx".as_bytes(), "uw".into()).is_err());
     }
 }
@@ -0,0 +1,163 @@
+use std::{cmp::Reverse, io::BufRead, ops::Range};
+
+use regex::Regex;
+
+use crate::{Error, Result};
+
+/// Finds tokens in the raw text that must be kept as single words, before
+/// the text is fed to the model. With a [`crate::Normalization`] other than
+/// `None` or a [`crate::T2S`] conversion, recognizers see the converted text
+/// instead. See [`crate::Thulac::add_recognizer`].
+pub trait Recognizer: Send + Sync {
+    /// Pushes the byte range in `raw` and the tag of each token found.
+    /// Ranges may overlap those of other recognizers; the leftmost and then
+    /// longest one wins.
+    fn recognize<'a>(&'a self, raw: &str, spans: &mut Vec<(Range<usize>, &'a str)>);
+}
+
+/// Recognizes every non-overlapping match of each of its regular
+/// expressions, tagging it with the tag given with the expression. If an
+/// expression has capture groups, the token is the first group instead of
+/// the whole match.
+#[derive(Default)]
+pub struct PatternRecognizer {
+    patterns: Vec<(Regex, String)>,
+}
+
+const URL: &str = r#"(?i)(?:(?-u:\b)(?:https?|ftp)://|(?-u:\b)www\.)[^\s<>"'，。！？；：、（）【】《》“”‘’]*[^\s<>"'，。！？；：、（）【】《》“”‘’.,;:!?)\]]"#;
+const EMAIL: &str =
+    r"(?-u:\b)[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}(?-u:\b)";
+const MENTION: &str = r"@[A-Za-z0-9_-]+";
+const HASHTAG: &str = r"#[^#\s\d][^#\s]{0,31}#|#[A-Za-z_][A-Za-z0-9_]*";
+
+impl PatternRecognizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// URLs tagged `url`, email addresses tagged `email`, `@name` mentions
+    /// tagged `mention` and `#topic#` or `#topic` hashtags tagged `hashtag`.
+    pub fn web() -> Self {
+        let mut recognizer = Self::new();
+        for (pattern, tag) in [
+            (URL, "url"),
+            (EMAIL, "email"),
+            (MENTION, "mention"),
+            (HASHTAG, "hashtag"),
+        ] {
+            recognizer.add(pattern, tag.into()).unwrap();
+        }
+        recognizer
+    }
+    pub fn add(&mut self, pattern: &str, tag: String) -> std::result::Result<(), regex::Error> {
+        self.patterns.push((Regex::new(pattern)?, tag));
+        Ok(())
+    }
+    /// Loads lines of `tag\tpattern`. Empty lines are skipped.
+    pub fn load_txt<R: BufRead>(reader: &mut R) -> Result<Self> {
+        let mut recognizer = Self::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |reason| Error::InvalidPattern {
+                line: i + 1,
+                reason,
+            };
+            let (tag, pattern) = line
+                .split_once('\t')
+                .ok_or_else(|| invalid("missing delimiter".into()))?;
+            recognizer
+                .add(pattern, tag.into())
+                .map_err(|e| invalid(e.to_string()))?;
+        }
+        Ok(recognizer)
+    }
+}
+
+impl Recognizer for PatternRecognizer {
+    fn recognize<'a>(&'a self, raw: &str, spans: &mut Vec<(Range<usize>, &'a str)>) {
+        for (regex, tag) in self.patterns.iter() {
+            if regex.captures_len() > 1 {
+                spans.extend(
+                    regex
+                        .captures_iter(raw)
+                        .filter_map(|x| x.get(1))
+                        .map(|x| (x.range(), &tag[..])),
+                );
+            } else {
+                spans.extend(regex.find_iter(raw).map(|x| (x.range(), &tag[..])));
+            }
+        }
+    }
+}
+
+/// Whether the token at `range` starts or ends with an ASCII letter or digit
+/// glued to another one, as `13` in `iPhone13`.
+pub(crate) fn glued_to_ascii(raw: &str, range: &Range<usize>) -> bool {
+    let alnum = |ch: Option<char>| ch.is_some_and(|ch| ch.is_ascii_alphanumeric());
+    let (before, word, after) = (&raw[..range.start], &raw[range.clone()], &raw[range.end..]);
+    alnum(before.chars().next_back()) && alnum(word.chars().next())
+        || alnum(word.chars().next_back()) && alnum(after.chars().next())
+}
+
+/// Sorts `spans` and keeps the leftmost and then longest of overlapping
+/// ones. Empty spans and spans containing whitespace are dropped.
+pub(crate) fn resolve<'a>(
+    raw: &str,
+    mut spans: Vec<(Range<usize>, &'a str)>,
+) -> Vec<(Range<usize>, &'a str)> {
+    spans.retain(|(range, _)| {
+        !range.is_empty() && !raw[range.clone()].chars().any(char::is_whitespace)
+    });
+    spans.sort_by_key(|(range, _)| (range.start, Reverse(range.end)));
+    let mut end = 0;
+    spans.retain(|(range, _)| {
+        if range.start < end {
+            return false;
+        }
+        end = range.end;
+        true
+    });
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recognize<'a>(recognizer: &'a PatternRecognizer, raw: &'a str) -> Vec<(&'a str, &'a str)> {
+        let mut spans = Vec::new();
+        recognizer.recognize(raw, &mut spans);
+        resolve(raw, spans)
+            .into_iter()
+            .map(|(range, tag)| (&raw[range], tag))
+            .collect()
+    }
+
+    #[test]
+    fn test_web() {
+        let web = PatternRecognizer::web();
+        assert_eq!(
+            recognize(&web, "看https://x.com/a?b=1。"),
+            [("https://x.com/a?b=1", "url")]
+        );
+        assert_eq!(
+            recognize(&web, "(见www.tsinghua.edu.cn)"),
+            [("www.tsinghua.edu.cn", "url")]
+        );
+        assert_eq!(
+            recognize(&web, "发到foo@bar.com吧"),
+            [("foo@bar.com", "email")]
+        );
+        assert_eq!(
+            recognize(&web, "@Alice 你看#清华# #rust"),
+            [
+                ("@Alice", "mention"),
+                ("#清华#", "hashtag"),
+                ("#rust", "hashtag")
+            ]
+        );
+        assert_eq!(recognize(&web, "#123456"), []);
+    }
+}
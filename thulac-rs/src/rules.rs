@@ -0,0 +1,322 @@
+use std::{io::BufRead, ops::Range};
+
+use regex::Regex;
+
+use crate::{parse_gold, post::concat_str, Error, Result};
+
+/// Matches a word by its text and its tag, either of which may be omitted.
+struct TokenPattern {
+    text: Option<Regex>,
+    tag: Option<Regex>,
+}
+
+enum Action {
+    /// Merges the matched words, tagged with the tag given or the tag of
+    /// the last word.
+    Merge(Option<String>),
+    /// Sets the tag of each matched word, `None` keeping it.
+    Retag(Vec<Option<String>>),
+    /// Splits a single matched word into parts of the given numbers of
+    /// chars, with optional tags.
+    Split(Vec<(usize, Option<String>)>),
+}
+
+struct Rule {
+    tokens: Vec<TokenPattern>,
+    action: Action,
+}
+
+/// Rules rewriting sequences of words after post-processors, applied one
+/// after another in the order they were loaded. Each rule rewrites its
+/// non-overlapping matches from left to right. Matches never span
+/// whitespace.
+#[derive(Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+/// Compiles `pattern` to match whole strings, or `None` if it is empty.
+fn anchored(pattern: &str) -> std::result::Result<Option<Regex>, regex::Error> {
+    if pattern.is_empty() {
+        return Ok(None);
+    }
+    Regex::new(&format!("^(?:{})$", pattern)).map(Some)
+}
+
+/// Parses `TEXT/TAG`, where either regex may be empty and `\/` is a `/`
+/// of the text.
+fn parse_token(token: &str) -> std::result::Result<TokenPattern, String> {
+    let mut escaped = false;
+    let mut split = None;
+    for (i, ch) in token.char_indices() {
+        match ch {
+            '\\' => escaped = !escaped,
+            '/' if !escaped => split = Some(i),
+            _ => escaped = false,
+        }
+    }
+    let (text, tag) = match split {
+        Some(i) => (&token[..i], &token[i + 1..]),
+        None => (token, ""),
+    };
+    let (text, tag) = (anchored(text), anchored(tag));
+    Ok(TokenPattern {
+        text: text.map_err(|e| e.to_string())?,
+        tag: tag.map_err(|e| e.to_string())?,
+    })
+}
+
+/// Parses a tag of an action, `-` or nothing keeping the current one.
+fn parse_tag(tag: Option<&str>) -> Option<String> {
+    tag.filter(|&x| !x.is_empty() && x != "-")
+        .map(str::to_owned)
+}
+
+fn parse_action(action: &str, tokens: usize) -> std::result::Result<Action, String> {
+    let mut fields = action.split_whitespace();
+    match fields.next() {
+        Some("merge") if tokens >= 2 => {
+            let tag = parse_tag(fields.next());
+            if fields.next().is_some() {
+                return Err("merge takes at most one tag".into());
+            }
+            Ok(Action::Merge(tag))
+        }
+        Some("merge") => Err("merge needs at least two words".into()),
+        Some("retag") => {
+            let tags = fields.map(|x| parse_tag(Some(x))).collect::<Vec<_>>();
+            if tags.len() != tokens {
+                return Err("retag needs one tag per word".into());
+            }
+            Ok(Action::Retag(tags))
+        }
+        Some("split") if tokens == 1 => {
+            let parts = fields
+                .map(|x| {
+                    let (len, tag) = x.split_once('_').map_or((x, None), |(x, y)| (x, Some(y)));
+                    let len = len.parse::<usize>().ok().filter(|&x| x != 0);
+                    len.map(|x| (x, parse_tag(tag)))
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or("split takes lengths in chars, like `2_ns 3`")?;
+            if parts.len() < 2 {
+                return Err("split needs at least two parts".into());
+            }
+            Ok(Action::Split(parts))
+        }
+        Some("split") => Err("split matches a single word".into()),
+        _ => Err("unknown action".into()),
+    }
+}
+
+impl Rule {
+    /// The number of words matched at the start of `words`.
+    fn matches(&self, words: &[(Range<usize>, &str, &str)]) -> Option<usize> {
+        let n = self.tokens.len();
+        let matched = words.len() >= n
+            && self
+                .tokens
+                .iter()
+                .zip(words)
+                .all(|(token, (_, word, tag))| {
+                    !word.is_empty()
+                        && token.text.as_ref().is_none_or(|x| x.is_match(word))
+                        && token.tag.as_ref().is_none_or(|x| x.is_match(tag))
+                });
+        matched.then_some(n)
+    }
+    fn rewrite<'a, 'b>(
+        &'b self,
+        words: &[(Range<usize>, &'a str, &'b str)],
+        result: &mut Vec<(Range<usize>, &'a str, &'b str)>,
+    ) {
+        match &self.action {
+            Action::Merge(tag) => {
+                let (first, rest) = words.split_first().unwrap();
+                let (range, word) = rest.iter().fold(
+                    (first.0.clone(), first.1),
+                    |(acc_range, acc_word), (range, word, _)| {
+                        assert!(acc_range.end == range.start);
+                        (acc_range.start..range.end, unsafe {
+                            concat_str(acc_word, word)
+                        })
+                    },
+                );
+                let last = words.last().unwrap().2;
+                result.push((range, word, tag.as_deref().unwrap_or(last)));
+            }
+            Action::Retag(tags) => {
+                result.extend(words.iter().zip(tags).map(|((range, word, tag), new)| {
+                    (range.clone(), *word, new.as_deref().unwrap_or(tag))
+                }));
+            }
+            Action::Split(parts) => {
+                let (range, word, tag) = &words[0];
+                let chars = word.char_indices().map(|x| x.0).chain([word.len()]);
+                let offsets = chars.collect::<Vec<_>>();
+                let total = parts.iter().map(|x| x.0).sum::<usize>();
+                // normalized words cannot be split within the raw text
+                if total + 1 != offsets.len() || word.len() != range.len() {
+                    result.push(words[0].clone());
+                    return;
+                }
+                let mut start = 0;
+                for (len, new) in parts {
+                    let (from, to) = (offsets[start], offsets[start + len]);
+                    result.push((
+                        range.start + from..range.start + to,
+                        &word[from..to],
+                        new.as_deref().unwrap_or(tag),
+                    ));
+                    start += len;
+                }
+            }
+        }
+    }
+    fn apply<'a, 'b>(
+        &'b self,
+        words: Vec<(Range<usize>, &'a str, &'b str)>,
+    ) -> Vec<(Range<usize>, &'a str, &'b str)> {
+        let mut result = Vec::with_capacity(words.len());
+        let mut i = 0;
+        while i < words.len() {
+            match self.matches(&words[i..]) {
+                Some(n) => {
+                    self.rewrite(&words[i..i + n], &mut result);
+                    i += n;
+                }
+                None => {
+                    result.push(words[i].clone());
+                    i += 1;
+                }
+            }
+        }
+        result
+    }
+}
+
+impl RuleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Loads lines of `pattern\taction`, where the pattern is a sequence of
+    /// words `TEXT/TAG` separated by spaces, `TEXT` and `TAG` being regular
+    /// expressions matching whole texts and tags, either of which may be
+    /// empty. Actions are:
+    ///
+    /// - `merge [TAG]`, merging the words, tagged `TAG` or as the last word;
+    /// - `retag TAG…`, with one tag per word, `-` keeping the tag;
+    /// - `split LEN[_TAG]…`, splitting a single word into parts of `LEN`
+    ///   chars.
+    ///
+    /// A rule may be followed by tests, lines of `>\twords\texpected` in
+    /// `word_tag` format, checked by applying the rule alone. Empty lines
+    /// and lines starting with `#` are skipped.
+    /// For example, `\d+/m 楼/\tmerge s` makes `3_m 楼_n` one word `3楼_s`,
+    /// and can be tested by `>\t3_m 楼_n 的_u\t3楼_s 的_u`.
+    pub fn load_txt<R: BufRead>(reader: &mut R) -> Result<Self> {
+        let mut rules = Self::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason| Error::InvalidRule {
+                line: i + 1,
+                reason,
+            };
+            let (pattern, action) = line
+                .split_once('\t')
+                .ok_or_else(|| invalid("missing delimiter".into()))?;
+            if pattern == ">" {
+                let rule = rules
+                    .rules
+                    .last()
+                    .ok_or_else(|| invalid("test before any rule".into()))?;
+                check(rule, action).map_err(invalid)?;
+                continue;
+            }
+            let tokens = pattern
+                .split(' ')
+                .filter(|x| !x.is_empty())
+                .map(parse_token)
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(invalid)?;
+            if tokens.is_empty() {
+                return Err(invalid("empty pattern".into()));
+            }
+            let action = parse_action(action, tokens.len()).map_err(invalid)?;
+            rules.rules.push(Rule { tokens, action });
+        }
+        Ok(rules)
+    }
+    pub fn apply<'a, 'b>(
+        &'b self,
+        mut words: Vec<(Range<usize>, &'a str, &'b str)>,
+    ) -> Vec<(Range<usize>, &'a str, &'b str)> {
+        for rule in self.rules.iter() {
+            words = rule.apply(words);
+        }
+        words
+    }
+}
+
+/// Checks a test of `rule`, `words\texpected` in `word_tag` format.
+fn check(rule: &Rule, test: &str) -> std::result::Result<(), String> {
+    let (words, expected) = test.split_once('\t').ok_or("missing expected words")?;
+    let (raw, words) = parse_gold(words, "_").ok_or("invalid words of test")?;
+    let words = words
+        .into_iter()
+        .map(|(range, _, tag)| (range.clone(), &raw[range], tag))
+        .collect::<Vec<_>>();
+    let actual = rule
+        .apply(words)
+        .into_iter()
+        .map(|(_, word, tag)| format!("{}_{}", word, tag))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if actual.split(' ').eq(expected.split_whitespace()) {
+        Ok(())
+    } else {
+        Err(format!("test failed, got `{}`", actual))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let rules = "# floors\n\
+                     \\d+/m 楼/\tmerge s\n\
+                     >\t3_m 楼_n 的_u\t3楼_s 的_u\n\
+                     \\p{Han}{1,3}/nr 老师/\tmerge np\n\
+                     >\t王_nr 老师_n\t王老师_np\n\
+                     /ns\tretag nz\n\
+                     清华大学生/\tsplit 2_ni 3_n\n\
+                     >\t清华大学生_n\t清华_ni 大学生_n\n";
+        let rules = RuleSet::load_txt(&mut rules.as_bytes()).unwrap();
+        let (_, words) = crate::tests::gold("3_m 楼_n 北京_ns 王_nr 老师_n");
+        assert_eq!(
+            rules.apply(words),
+            [
+                (0..4, "3楼", "s"),
+                (4..10, "北京", "nz"),
+                (10..19, "王老师", "np")
+            ]
+        );
+        for (rules, line) in [
+            ("a/\tmerge", 1),
+            ("a/ b/\tsplit 1 1", 1),
+            ("a/ b/\tretag x", 1),
+            ("(/\tretag x", 1),
+            ("a/ b/\tmerge x\n>\ta_n b_n\ta_n b_n", 2),
+        ] {
+            assert!(matches!(
+                RuleSet::load_txt(&mut rules.as_bytes()),
+                Err(Error::InvalidRule { line: x, .. }) if x == line
+            ));
+        }
+    }
+}
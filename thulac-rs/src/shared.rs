@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::{PostProcessor, PostProcessorId, Thulac};
+
+/// A [`Thulac`] whose post-processors can be replaced while other threads
+/// segment with it. Each change swaps in a modified clone atomically; calls
+/// already running keep the instance they got from [`SharedThulac::load`].
+pub struct SharedThulac {
+    current: ArcSwap<Thulac>,
+}
+
+impl SharedThulac {
+    pub fn new(thulac: Thulac) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(thulac),
+        }
+    }
+    /// The current instance, unaffected by later changes.
+    pub fn load(&self) -> Arc<Thulac> {
+        self.current.load_full()
+    }
+    /// Replaces the post-processor `id` of the current instance and returns
+    /// the old one, or `None` if there is no such post-processor.
+    pub fn replace_postprocessor(
+        &self,
+        id: PostProcessorId,
+        post: PostProcessor,
+    ) -> Option<Arc<PostProcessor>> {
+        let post = Arc::new(post);
+        let mut old = None;
+        self.current.rcu(|current| {
+            let mut next = Thulac::clone(current);
+            old = next.replace_postprocessor_arc(id, post.clone());
+            next
+        });
+        old
+    }
+    /// Removes the post-processor `id` of the current instance and returns
+    /// it, or `None` if there is no such post-processor.
+    pub fn remove_postprocessor(&self, id: PostProcessorId) -> Option<Arc<PostProcessor>> {
+        let mut old = None;
+        self.current.rcu(|current| {
+            let mut next = Thulac::clone(current);
+            old = next.remove_postprocessor(id);
+            next
+        });
+        old
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::dummy;
+
+    #[test]
+    fn test_shared_thulac() {
+        let dict = |x: &str| PostProcessor::load_txt(&mut x.as_bytes(), "uw".into()).unwrap();
+        let mut thulac = dummy();
+        let first = thulac.add_postprocessor(dict("北京大学\tni"));
+        let id = thulac.add_postprocessor(dict("清华大学\tni"));
+        let shared = SharedThulac::new(thulac);
+        let in_flight = shared.load();
+        assert!(shared.remove_postprocessor(first).is_some());
+        assert!(shared
+            .replace_postprocessor(id, dict("清华大学\tnt"))
+            .is_some());
+        assert!(shared
+            .replace_postprocessor(first, dict("清华大学\tns"))
+            .is_none());
+        let tag = |thulac: &Thulac| {
+            let preprocess = thulac.preprocess("清华大学");
+            thulac.cut(&preprocess)[0].2.to_owned()
+        };
+        assert_eq!(tag(&in_flight), "ni");
+        assert_eq!(tag(&shared.load()), "nt");
+        assert_eq!(in_flight.postprocessors().count(), 2);
+        assert_eq!(shared.load().postprocessors().count(), 1);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Error, Result},
+    ops::Range,
+};
+
+use crate::Dat;
+
+/// Byte ranges of the parts of a compound, with optional tags.
+type Parts = Vec<(Range<usize>, Option<String>)>;
+
+/// Splits words the model merged wrongly, before post-processors merge
+/// words. Words are split either as a whole, when a compound maps to its
+/// parts, or wherever they contain a forced boundary. Words normalized to a
+/// different length than their raw text are left alone.
+#[derive(Default)]
+pub struct Splitter {
+    compounds: HashMap<String, Parts>,
+    /// Boundary patterns followed by `\0`, whose values index `boundaries`.
+    patterns: Option<Dat>,
+    /// Byte offsets of the boundaries in each pattern.
+    boundaries: Vec<Vec<usize>>,
+}
+
+impl Splitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Splits `compound` into `parts`, which must add up to it. Parts
+    /// without a tag get the tag of the compound.
+    pub fn add_compound(&mut self, compound: &str, parts: &[(&str, Option<&str>)]) -> Result<()> {
+        if parts.iter().map(|x| x.0).collect::<String>() != compound
+            || parts.iter().any(|x| x.0.is_empty())
+        {
+            return Err(Error::other("parts do not add up to the compound"));
+        }
+        let mut start = 0;
+        let parts = parts
+            .iter()
+            .map(|(part, tag)| {
+                start += part.len();
+                (start - part.len()..start, tag.map(str::to_owned))
+            })
+            .collect();
+        self.compounds.insert(compound.to_owned(), parts);
+        Ok(())
+    }
+    /// Sets the patterns whose boundaries, marked with `|` as in
+    /// `清华|大学生`, are forced wherever they occur in a word.
+    pub fn set_boundaries<'a, I: IntoIterator<Item = &'a str>>(
+        &mut self,
+        patterns: I,
+    ) -> Result<()> {
+        let mut keys = HashMap::new();
+        self.boundaries.clear();
+        for pattern in patterns {
+            let mut key = String::new();
+            let mut boundaries = Vec::new();
+            for piece in pattern.split('|') {
+                if piece.is_empty() {
+                    return Err(Error::other("empty piece of boundary pattern"));
+                }
+                key.push_str(piece);
+                boundaries.push(key.len());
+            }
+            boundaries.pop();
+            if boundaries.is_empty() {
+                return Err(Error::other("boundary pattern without boundary"));
+            }
+            key.push('\0');
+            keys.entry(key).or_insert_with(|| {
+                self.boundaries.push(boundaries);
+                self.boundaries.len() as i32 - 1
+            });
+        }
+        self.patterns = (!keys.is_empty())
+            .then(|| Dat::build(keys.iter().map(|(x, &v)| (&x[..], v)).collect()));
+        Ok(())
+    }
+    /// Loads lines of either `compound\tpart part…`, where parts may be
+    /// tagged as `part_tag`, or boundary patterns like `清华|大学生`. Empty
+    /// lines are skipped.
+    pub fn load_txt<R: BufRead>(reader: &mut R) -> Result<Self> {
+        let mut splitter = Self::new();
+        let mut patterns = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            match line.split_once('\t') {
+                Some((compound, parts)) => {
+                    let parts = parts
+                        .split(' ')
+                        .filter(|x| !x.is_empty())
+                        .map(|x| match x.rsplit_once('_') {
+                            Some((part, tag)) if !part.is_empty() && !tag.is_empty() => {
+                                (part, Some(tag))
+                            }
+                            _ => (x, None),
+                        })
+                        .collect::<Vec<_>>();
+                    splitter.add_compound(compound, &parts)?;
+                }
+                None => patterns.push(line),
+            }
+        }
+        splitter.set_boundaries(patterns.iter().map(String::as_str))?;
+        Ok(splitter)
+    }
+    /// Byte offsets in `word`, other than its ends, of the forced
+    /// boundaries it contains, sorted.
+    fn cuts(&self, word: &str) -> Vec<usize> {
+        let Some(patterns) = self.patterns.as_ref() else {
+            return Vec::new();
+        };
+        let mut cuts = Vec::new();
+        for (start, _) in word.char_indices() {
+            let mut node = patterns.root();
+            for ch in word[start..].chars() {
+                node = match patterns.child(node, ch) {
+                    Some(x) => x,
+                    None => break,
+                };
+                if let Some(leaf) = patterns.child(node, '\0') {
+                    let boundaries = &self.boundaries[patterns.base(leaf) as usize];
+                    cuts.extend(boundaries.iter().map(|x| start + x));
+                }
+            }
+        }
+        cuts.retain(|&x| x != word.len());
+        cuts.sort_unstable();
+        cuts.dedup();
+        cuts
+    }
+    pub fn split<'a, 'b>(
+        &'b self,
+        words: Vec<(Range<usize>, &'a str, &'b str)>,
+    ) -> Vec<(Range<usize>, &'a str, &'b str)> {
+        let mut result = Vec::with_capacity(words.len());
+        for (range, word, tag) in words {
+            if word.is_empty() || word.len() != range.len() {
+                result.push((range, word, tag));
+            } else if let Some(parts) = self.compounds.get(word) {
+                result.extend(parts.iter().map(|(part, part_tag)| {
+                    (
+                        range.start + part.start..range.start + part.end,
+                        &word[part.clone()],
+                        part_tag.as_deref().unwrap_or(tag),
+                    )
+                }));
+            } else {
+                let mut last = 0;
+                for cut in self.cuts(word).into_iter().chain([word.len()]) {
+                    result.push((range.start + last..range.start + cut, &word[last..cut], tag));
+                    last = cut;
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let rules = "清华大学生\t清华_ni 大学生\n\n|大学生|\n学|生会\n";
+        assert!(Splitter::load_txt(&mut rules.as_bytes()).is_err());
+        let rules = "清华大学生\t清华_ni 大学生\n北大|学生\n学|生会\n";
+        let splitter = Splitter::load_txt(&mut rules.as_bytes()).unwrap();
+        let input = "清华大学生 北大学生会";
+        let words = vec![
+            (0..15, &input[0..15], "n"),
+            (15..16, "", "w"),
+            (16..31, &input[16..31], "n"),
+        ];
+        assert_eq!(
+            splitter.split(words),
+            [
+                (0..6, "清华", "ni"),
+                (6..15, "大学生", "n"),
+                (15..16, "", "w"),
+                (16..22, "北大", "n"),
+                (22..25, "学", "n"),
+                (25..31, "生会", "n"),
+            ]
+        );
+        assert!(Splitter::load_txt(&mut "清华大学\t清华 大".as_bytes()).is_err());
+    }
+}
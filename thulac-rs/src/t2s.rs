@@ -1,33 +1,220 @@
 use std::{
     collections::HashMap,
-    io::{Error, ErrorKind, Read, Result, Seek},
+    io::{BufRead, Error, Read, Result, Seek, Write},
     mem::size_of,
-    slice,
+    ops::Range,
 };
 
+use crate::Dat;
+
+/// Converts traditional Chinese to simplified, phrase by phrase and then
+/// char by char. The same tables in the other direction make an [`S2T`].
+#[derive(Default)]
 pub struct T2S {
     t2s: HashMap<char, char>,
+    phrases: Vec<(String, String)>,
+    /// Phrases followed by `\0`, whose values index `phrases`.
+    phrase_dat: Option<Dat>,
+}
+
+/// Converts simplified Chinese to traditional, with tables such as OpenCC's
+/// `STCharacters.txt` and `STPhrases.txt`.
+pub type S2T = T2S;
+
+/// Parses a line of `source\ttarget`, keeping only the first of several
+/// space separated targets.
+fn parse_line(line: &str) -> Result<(&str, &str)> {
+    let (source, targets) = line
+        .split_once('\t')
+        .ok_or_else(|| Error::other("missing delimiter"))?;
+    let target = targets
+        .split(' ')
+        .next()
+        .filter(|x| !x.is_empty())
+        .ok_or_else(|| Error::other("missing conversion"))?;
+    Ok((source, target))
 }
 
 impl T2S {
+    pub fn new() -> Self {
+        Self::default()
+    }
     pub fn load<R: Read + Seek>(reader: &mut R) -> Result<Self> {
         let len = reader.stream_len()? as usize;
-        if len % (2 * size_of::<char>()) != 0 {
-            return Err(Error::new(ErrorKind::Other, "file size unexpected"));
-        }
-        let count = len / 2 / size_of::<char>();
-        let mut tra = vec!['\0'; count];
-        let mut sim = vec!['\0'; count];
-        reader.read_exact(unsafe {
-            slice::from_raw_parts_mut(tra.as_mut_ptr() as *mut u8, len / 2)
-        })?;
-        reader.read_exact(unsafe {
-            slice::from_raw_parts_mut(sim.as_mut_ptr() as *mut u8, len / 2)
-        })?;
+        if !len.is_multiple_of(2 * size_of::<u32>()) {
+            return Err(Error::other("file size unexpected"));
+        }
+        let mut bytes = vec![0; len];
+        reader.read_exact(&mut bytes)?;
+        let chars = bytes
+            .chunks_exact(size_of::<u32>())
+            .map(|x| {
+                char::from_u32(u32::from_ne_bytes(x.try_into().unwrap()))
+                    .ok_or_else(|| Error::other("invalid char"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let (tra, sim) = chars.split_at(chars.len() / 2);
         Ok(Self {
-            t2s: tra.into_iter().zip(sim.into_iter()).collect(),
+            t2s: tra.iter().copied().zip(sim.iter().copied()).collect(),
+            ..Self::default()
         })
     }
+    /// Writes the char table in the format read by [`T2S::load`]. Phrases
+    /// are not included, see [`T2S::save_phrases_txt`].
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut pairs = self.t2s.iter().collect::<Vec<_>>();
+        pairs.sort_unstable();
+        for ch in pairs.iter().map(|x| *x.0).chain(pairs.iter().map(|x| *x.1)) {
+            writer.write_all(&(ch as u32).to_ne_bytes())?;
+        }
+        Ok(())
+    }
+    /// Loads lines of `source\ttarget` as in OpenCC dictionaries, so both
+    /// `TSCharacters.txt` and `TSPhrases.txt` can be read. Lines mapping a
+    /// char to a char go to the char table, others are phrases. Only the
+    /// first of several space separated targets and the first line of a
+    /// source are used. Empty lines are skipped.
+    pub fn load_txt<R: BufRead>(reader: &mut R) -> Result<Self> {
+        let mut t2s = Self::new();
+        t2s.extend_txt(reader)?;
+        Ok(t2s)
+    }
+    /// Adds the mappings of a file read as in [`T2S::load_txt`].
+    pub fn extend_txt<R: BufRead>(&mut self, reader: &mut R) -> Result<()> {
+        let mut phrases = std::mem::take(&mut self.phrases);
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let (source, target) = parse_line(&line)?;
+            let mut chars = source.chars().zip(target.chars());
+            match (chars.next(), source.chars().nth(1), target.chars().nth(1)) {
+                (Some((source, target)), None, None) => {
+                    self.t2s.entry(source).or_insert(target);
+                }
+                _ => phrases.push((source.to_owned(), target.to_owned())),
+            }
+        }
+        self.set_phrases(phrases);
+        Ok(())
+    }
+    /// Writes the phrases as read by [`T2S::load_phrases_txt`].
+    pub fn save_phrases_txt<W: Write>(&self, writer: &mut W) -> Result<()> {
+        for (source, target) in self.phrases.iter() {
+            writeln!(writer, "{}\t{}", source, target)?;
+        }
+        Ok(())
+    }
+    /// The same conversion in the other direction. Where several sources
+    /// share a target, as `乾`, `幹` and `干` for `干`, the first wins, so
+    /// dedicated tables are better when available.
+    pub fn reversed(&self) -> S2T {
+        let mut pairs = self.t2s.iter().collect::<Vec<_>>();
+        pairs.sort_unstable();
+        let mut reversed = Self::new();
+        for (&source, &target) in pairs {
+            reversed.t2s.entry(target).or_insert(source);
+        }
+        reversed.set_phrases(
+            self.phrases
+                .iter()
+                .map(|(source, target)| (target.clone(), source.clone())),
+        );
+        reversed
+    }
+    /// Adds phrases converted as a whole, longest first, before chars are
+    /// converted one by one, like `軟體` to `软件` or `乾燥` to `干燥`. Only
+    /// the first mapping of a phrase is kept.
+    pub fn set_phrases<I: IntoIterator<Item = (String, String)>>(&mut self, phrases: I) {
+        let mut sources = HashMap::new();
+        self.phrases.clear();
+        for (source, target) in phrases {
+            let key = source.clone() + "\0";
+            if source.is_empty() || sources.contains_key(&key) {
+                continue;
+            }
+            sources.insert(key, self.phrases.len() as i32);
+            self.phrases.push((source, target));
+        }
+        self.phrase_dat = (!sources.is_empty())
+            .then(|| Dat::build(sources.iter().map(|(x, &v)| (&x[..], v)).collect()));
+    }
+    /// Loads phrases from lines of `phrase\tconverted`, as in OpenCC
+    /// dictionaries. Only the first of several space separated conversions
+    /// is used.
+    pub fn load_phrases_txt<R: BufRead>(&mut self, reader: &mut R) -> Result<()> {
+        let phrases = reader
+            .lines()
+            .filter(|x| x.as_ref().map_or(true, |x| !x.is_empty()))
+            .map(|x| {
+                let x = x?;
+                let (source, target) = parse_line(&x)?;
+                Ok((source.to_owned(), target.to_owned()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.set_phrases(phrases);
+        Ok(())
+    }
+    /// Converts chars paired with the byte range of the raw text they come
+    /// from. Chars of a phrase converted to a different length all get the
+    /// range of the whole phrase. Phrases are only matched on whole ranges.
+    pub fn convert(&self, chars: &[(char, Range<usize>)]) -> Vec<(char, Range<usize>)> {
+        let boundary =
+            |i: usize| i == 0 || i == chars.len() || chars[i - 1].1.end <= chars[i].1.start;
+        let mut result = Vec::with_capacity(chars.len());
+        let mut i = 0;
+        while i < chars.len() {
+            if let Some((end, target)) = self.phrase_at(chars, i).filter(|_| boundary(i)) {
+                let target = &self.phrases[target].1;
+                if target.chars().count() == end - i {
+                    result.extend(
+                        target
+                            .chars()
+                            .zip(chars[i..end].iter().map(|x| x.1.clone())),
+                    );
+                } else {
+                    let range = chars[i].1.start..chars[end - 1].1.end;
+                    result.extend(target.chars().map(|ch| (ch, range.clone())));
+                }
+                i = end;
+                continue;
+            }
+            let (ch, range) = &chars[i];
+            result.push((self.t2s.get(ch).copied().unwrap_or(*ch), range.clone()));
+            i += 1;
+        }
+        result
+    }
+    /// The end and target index of the longest phrase starting at `start`
+    /// and ending on a whole range.
+    fn phrase_at(&self, chars: &[(char, Range<usize>)], start: usize) -> Option<(usize, usize)> {
+        let phrases = self.phrase_dat.as_ref()?;
+        let mut node = phrases.root();
+        let mut best = None;
+        for (i, (ch, _)) in chars.iter().enumerate().skip(start) {
+            node = match phrases.child(node, *ch) {
+                Some(x) => x,
+                None => break,
+            };
+            let end = i + 1;
+            if end == chars.len() || chars[end - 1].1.end <= chars[end].1.start {
+                if let Some(leaf) = phrases.child(node, '\0') {
+                    best = Some((end, phrases.base(leaf) as usize));
+                }
+            }
+        }
+        best
+    }
+    /// Converts a whole text, as when showing results in the script of the
+    /// user.
+    pub fn convert_str(&self, text: &str) -> String {
+        let chars = text
+            .char_indices()
+            .map(|(i, ch)| (ch, i..i + ch.len_utf8()))
+            .collect::<Vec<_>>();
+        self.convert(&chars).into_iter().map(|x| x.0).collect()
+    }
     pub fn process<'a, I: IntoIterator<Item = char>>(
         &'a self,
         iter: I,
@@ -56,3 +243,54 @@ where
             .map(|x| self.t2s.get(&x).copied().unwrap_or(x))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(t2s: &T2S, raw: &str) -> (String, Vec<Range<usize>>) {
+        let chars = raw
+            .char_indices()
+            .map(|(i, ch)| (ch, i..i + ch.len_utf8()))
+            .collect::<Vec<_>>();
+        t2s.convert(&chars).into_iter().unzip()
+    }
+
+    #[test]
+    fn test_phrases() {
+        let mut t2s = T2S::load_txt(&mut "乾\t干\n體\t体\n後\t后 後".as_bytes()).unwrap();
+        let phrases = "軟體\t软件\n乾隆\t乾隆\n\n電腦程式\t计算机程序 电脑程序\n";
+        t2s.load_phrases_txt(&mut phrases.as_bytes()).unwrap();
+        assert_eq!(convert(&t2s, "乾隆").0, "乾隆");
+        assert_eq!(convert(&t2s, "乾燥後").0, "干燥后");
+        assert_eq!(convert(&t2s, "軟體"), ("软件".into(), vec![0..3, 3..6]));
+        assert_eq!(
+            convert(&t2s, "用電腦程式"),
+            (
+                "用计算机程序".into(),
+                vec![0..3, 3..15, 3..15, 3..15, 3..15, 3..15]
+            )
+        );
+        assert!(t2s.load_phrases_txt(&mut "軟體".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_txt() {
+        let txt = "軟\t软\n體\t体\n乾\t干 乾\n幹\t干\n軟體\t软件\n";
+        let t2s = T2S::load_txt(&mut txt.as_bytes()).unwrap();
+        assert_eq!(t2s.convert_str("軟體很乾"), "软件很干");
+        let mut bin = Vec::new();
+        t2s.save(&mut bin).unwrap();
+        let mut phrases = Vec::new();
+        t2s.save_phrases_txt(&mut phrases).unwrap();
+        assert_eq!(phrases, "軟體\t软件\n".as_bytes());
+        let mut loaded = T2S::load(&mut std::io::Cursor::new(bin)).unwrap();
+        loaded.load_phrases_txt(&mut &phrases[..]).unwrap();
+        assert_eq!(loaded.convert_str("軟體很乾"), "软件很干");
+        let s2t = t2s.reversed();
+        assert_eq!(s2t.convert_str("软件很干"), "軟體很乾");
+        assert!(T2S::load_txt(&mut "軟".as_bytes()).is_err());
+        let invalid = [0x9AD4u32, 0xD800].map(u32::to_ne_bytes).concat();
+        assert!(T2S::load(&mut std::io::Cursor::new(invalid)).is_err());
+    }
+}